@@ -0,0 +1,96 @@
+use std::env;
+use std::process;
+
+use mmb_parser::diff::{self, Change, Matching};
+use mmb_parser::index::{Kind, NameTable};
+use mmb_parser::{Mmb, MmbFile};
+
+fn usage() -> ! {
+    eprintln!("usage: diff [--by-position] <old.mmb> <new.mmb>");
+    process::exit(2);
+}
+
+fn open(path: &str) -> MmbFile {
+    MmbFile::open(path).unwrap_or_else(|e| {
+        eprintln!("{}: {}", path, e);
+        process::exit(2);
+    })
+}
+
+fn parse<'a>(path: &str, file: &'a MmbFile) -> Mmb<'a> {
+    file.mmb().unwrap_or_else(|| {
+        eprintln!("{}: not a valid proof file", path);
+        process::exit(2);
+    })
+}
+
+fn label(names: Option<&NameTable>, kind: Kind, idx: u64) -> String {
+    let kind_name = match kind {
+        Kind::Sort => "sort",
+        Kind::Term => "term",
+        Kind::Theorem => "theorem",
+    };
+
+    let name = names
+        .and_then(|names| names.section(kind).get(idx))
+        .and_then(|name| name.to_str().ok());
+
+    match name {
+        Some(name) => format!("{} {}", kind_name, name),
+        None => format!("{} #{}", kind_name, idx),
+    }
+}
+
+fn main() {
+    let mut matching = Matching::ByName;
+    let mut paths = Vec::new();
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--by-position" => matching = Matching::ByPosition,
+            _ if arg.starts_with('-') => usage(),
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.len() != 2 {
+        usage();
+    }
+
+    let (old_file, new_file) = (open(&paths[0]), open(&paths[1]));
+    let old = parse(&paths[0], &old_file);
+    let new = parse(&paths[1], &new_file);
+
+    let old_names = old.index().and_then(|index| index.name_table());
+    let new_names = new.index().and_then(|index| index.name_table());
+
+    let changes = diff::diff(&old, &new, matching).unwrap_or_else(|| {
+        eprintln!("both files need a name table to match by name, try --by-position");
+        process::exit(2);
+    });
+
+    let old_label = |kind, idx| label(old_names.as_ref(), kind, idx);
+    let new_label = |kind, idx| label(new_names.as_ref(), kind, idx);
+
+    for change in &changes {
+        match *change {
+            Change::Added { kind, new } => println!("+ {}", new_label(kind, new)),
+            Change::Removed { kind, old } => println!("- {}", old_label(kind, old)),
+            Change::Renamed { kind, old, new } => {
+                println!(
+                    "renamed {} -> {}",
+                    old_label(kind, old),
+                    new_label(kind, new)
+                )
+            }
+            Change::Statement { kind, new, .. } => {
+                println!("statement changed: {}", new_label(kind, new))
+            }
+            Change::Proof { kind, new, .. } => println!("proof changed: {}", new_label(kind, new)),
+        }
+    }
+
+    if !changes.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,36 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use mmb_parser::cache::Cache;
+use mmb_parser::diff::{self, Matching};
+use mmb_parser::graph::DependencyGraph;
+use mmb_parser::hash::Hashes;
+use mmb_parser::stats::Stats;
+use mmb_parser::Mmb;
+
+fuzz_target!(|data: &[u8]| {
+    let mmb = match Mmb::from(data) {
+        Some(mmb) => mmb,
+        None => return,
+    };
+
+    let names = mmb.index().and_then(|index| index.name_table());
+
+    let stats = Stats::new(&mmb);
+    let _ = stats.report(names.as_ref(), 10).to_string();
+
+    let graph = DependencyGraph::new(&mmb);
+    let mut out = String::new();
+    let _ = graph.write_dot(&mut out, names.as_ref());
+    let _ = graph.write_json(&mut out, names.as_ref());
+    let _ = graph.unused();
+
+    for idx in 0..mmb.num_theorems().min(16) {
+        let _ = graph.axioms_used(idx);
+    }
+
+    let _ = Hashes::new(&mmb);
+    let _ = Cache::<()>::new().update(&mmb, |_, _, _| ());
+
+    let _ = diff::diff(&mmb, &mmb, Matching::ByName);
+    let _ = diff::diff(&mmb, &mmb, Matching::ByPosition);
+});
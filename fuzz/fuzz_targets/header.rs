@@ -0,0 +1,35 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use mmb_parser::Mmb;
+
+fuzz_target!(|data: &[u8]| {
+    let mmb = match Mmb::from(data) {
+        Some(mmb) => mmb,
+        None => return,
+    };
+
+    let _ = mmb.header();
+    let _ = mmb.proofs_ptr();
+
+    for idx in 0..mmb.num_terms() {
+        if let Some(term) = mmb.term(idx) {
+            let _ = (term.num_args(), term.sort_idx(), term.is_def());
+            term.binders().for_each(drop);
+            term.unify_commands().for_each(drop);
+        }
+    }
+
+    for idx in 0..mmb.num_theorems() {
+        if let Some(theorem) = mmb.theorem(idx) {
+            theorem.binders().for_each(drop);
+            theorem.unify_commands().for_each(drop);
+        }
+    }
+
+    let _ = mmb.term(u32::MAX);
+    let _ = mmb.theorem(u32::MAX);
+
+    for (_, _, statement) in mmb.declarations() {
+        statement.proof_commands().for_each(drop);
+    }
+});
@@ -0,0 +1,60 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use mmb_parser::index::Kind;
+use mmb_parser::{check, Mmb};
+
+fuzz_target!(|data: &[u8]| {
+    let mmb = match Mmb::from(data) {
+        Some(mmb) => mmb,
+        None => return,
+    };
+
+    let _ = check::statements(&mmb);
+
+    let index = match mmb.index() {
+        Some(index) => index,
+        None => return,
+    };
+
+    let _ = index.num_entries();
+    mmb.warnings().for_each(drop);
+
+    for entry in index {
+        if let Some(spans) = entry.as_source_spans(index) {
+            for id in 0..spans.num_files().min(64) {
+                let _ = spans.file_path(id);
+            }
+
+            for (kind, idx, _) in &spans {
+                let _ = spans.get(kind, idx);
+            }
+        }
+
+        if let Some(map) = entry.as_proof_source_map(index) {
+            for step in &map {
+                let _ = map.get(step.offset as usize);
+            }
+
+            for (_, statement) in (0..64).zip(mmb.statements()) {
+                for (offset, _) in statement.proof_commands() {
+                    let _ = map.get(offset);
+                }
+            }
+        }
+
+        let names = match entry.as_name_table(index) {
+            Some(names) => names,
+            None => continue,
+        };
+
+        let _ = check::name_table(&mmb, &names);
+
+        for name in &names {
+            let _ = name.to_str();
+        }
+
+        for &kind in &[Kind::Sort, Kind::Term, Kind::Theorem] {
+            names.section(kind).iter().for_each(drop);
+        }
+    }
+});
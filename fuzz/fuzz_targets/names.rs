@@ -0,0 +1,53 @@
+#![no_main]
+use std::convert::TryInto;
+
+use libfuzzer_sys::fuzz_target;
+use mmb_parser::index::Kind;
+use mmb_parser::Mmb;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 8 {
+        return;
+    }
+
+    // The first eight bytes select the entry that is looked up.
+    let (idx, file) = data.split_at(8);
+    let idx = u64::from_le_bytes(idx.try_into().unwrap());
+
+    let mmb = match Mmb::from(file) {
+        Some(mmb) => mmb,
+        None => return,
+    };
+
+    let names = match mmb.index().and_then(|index| index.name_table()) {
+        Some(names) => names,
+        None => return,
+    };
+
+    let sorted = names.sorted();
+
+    for &kind in &[Kind::Sort, Kind::Term, Kind::Theorem] {
+        let section = names.section(kind);
+
+        for idx in &[idx, idx.wrapping_neg(), u64::MAX, 0] {
+            if let Some(name) = section.get(*idx) {
+                let _ = name.to_str();
+
+                // Corrupt files may cause names to be missed, but lookups must
+                // never return an item with a different name.
+                if let Some((_, _, found)) = names.find(name.name) {
+                    assert_eq!(found.name, name.name);
+                }
+                let _ = names.find_kind(kind, name.name);
+
+                if let Some((_, _, found)) = sorted.find(name.name) {
+                    assert_eq!(found.name, name.name);
+                }
+                let _ = sorted.find_kind(kind, name.name);
+
+                let pattern = &name.name[..name.name.len().min(16)];
+                let _ = names.search(pattern);
+            }
+        }
+    }
+});
@@ -0,0 +1,122 @@
+#![no_main]
+use std::ops::ControlFlow;
+
+use libfuzzer_sys::fuzz_target;
+use mmb_parser::opcode::{Command, Proof, Statement, Unify};
+use mmb_parser::{Discard, Mmb, ProofStream, UnifyStream, VisitOptions, VisitResult, Visitor};
+
+#[derive(Default)]
+struct Stream {
+    len: usize,
+}
+
+impl UnifyStream for Stream {
+    fn push(&mut self, _: Command<Unify>) {
+        self.len += 1;
+    }
+
+    fn done(&self) -> (usize, usize) {
+        (0, self.len)
+    }
+}
+
+impl ProofStream for Stream {
+    fn push(&mut self, _: Command<Proof>) {
+        self.len += 1;
+    }
+
+    fn done(&self) -> (usize, usize) {
+        (0, self.len)
+    }
+}
+
+#[derive(Default)]
+struct Sink {
+    binders: Vec<u64>,
+    unify: Stream,
+    proof: Stream,
+}
+
+impl<'a> Visitor<'a> for Sink {
+    type Binder = u64;
+    type Sort = u8;
+    type Statement = Statement;
+    type Unify = Stream;
+    type Proof = Stream;
+    type Error = ();
+
+    fn parse_sort(&mut self, _: u8) -> VisitResult<()> {
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn parse_statement(
+        &mut self,
+        _: Statement,
+        _: usize,
+        _: &'a [u8],
+        _: Option<(usize, usize)>,
+    ) -> VisitResult<()> {
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn try_reserve_binder_slice(&mut self, nr: usize) -> Option<(&mut [u64], usize)> {
+        let offset = self.binders.len();
+        self.binders.resize(offset + nr, 0);
+
+        Some((&mut self.binders[offset..], offset))
+    }
+
+    fn start_unify_stream(&mut self) -> Option<&mut Stream> {
+        Some(&mut self.unify)
+    }
+
+    fn start_proof_stream(&mut self) -> Option<&mut Stream> {
+        Some(&mut self.proof)
+    }
+
+    fn parse_term(
+        &mut self,
+        _: u8,
+        _: (usize, usize),
+        _: u64,
+        _: &'a [u8],
+        _: (usize, usize),
+    ) -> VisitResult<()> {
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn parse_theorem(
+        &mut self,
+        _: (usize, usize),
+        _: &'a [u8],
+        _: (usize, usize),
+    ) -> VisitResult<()> {
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+/// A visitor that relies on the default implementations.
+struct Defaults;
+
+impl<'a> Visitor<'a> for Defaults {
+    type Binder = u64;
+    type Sort = u8;
+    type Statement = Statement;
+    type Unify = Discard;
+    type Proof = Discard;
+    type Error = ();
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Some(mmb) = Mmb::from(data) {
+        let _ = mmb.visit(&mut Sink::default());
+        let _ = mmb.visit(&mut Defaults);
+
+        let options = VisitOptions {
+            proofs: false,
+            ..Default::default()
+        };
+
+        let _ = mmb.visit_with(&mut Sink::default(), options);
+    }
+});
@@ -0,0 +1,360 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::graph::{DependencyGraph, Node};
+use crate::hash::{Fnv, Hashes};
+use crate::index::Kind;
+use crate::statement::StatementEntry;
+use crate::Mmb;
+
+/// The cache keys of all sorts, terms and theorems of a proof file.
+///
+/// The key of an item combines its statement and proof hashes with the proof
+/// hashes of the definitions it depends on, directly or through the bodies of
+/// other definitions, since a proof may unfold a definition and then the
+/// definitions in its body. The statements of dependencies are already part of
+/// the hashes of the item, so an item keeps its key as long as neither its own
+/// content nor the parts of its dependencies it can observe change, even if
+/// terms and theorems are added, removed or reordered around it.
+///
+/// Sorts are identified by their index, both in the key of a sort and in the
+/// hashes of the items that use it. Adding, removing or reordering sorts
+/// therefore changes the keys of the sorts that move and of all items using
+/// them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Keys {
+    sorts: Vec<u64>,
+    terms: Vec<u64>,
+    theorems: Vec<u64>,
+}
+
+impl Keys {
+    /// Compute the cache keys of a proof file.
+    pub fn new(mmb: &Mmb) -> Keys {
+        let hashes = Hashes::new(mmb);
+        let graph = DependencyGraph::new(mmb);
+
+        let sorts = (0..)
+            .zip(mmb.sorts())
+            .map(|(idx, &modifiers)| {
+                let mut h = Fnv::new();
+                h.write_u64(Kind::Sort as u64);
+                h.write_u64(idx);
+                h.write_u64(modifiers as u64);
+                h.finish()
+            })
+            .collect();
+
+        // The proof hashes of each term and of all definitions it depends on.
+        // A term can only depend on terms declared before it, so the hashes
+        // of its dependencies are already known, except in malformed files.
+        let mut definitions: Vec<u64> = Vec::with_capacity(mmb.num_terms() as usize);
+
+        for idx in 0..mmb.num_terms() {
+            let mut h = Fnv::new();
+            h.write_u64(hashes.term(idx).and_then(|term| term.proof).unwrap_or(0));
+
+            for &dep in graph.dependencies(Node::Term(idx)) {
+                if let Node::Term(dep) = dep {
+                    if let Some(&def) = definitions.get(dep as usize) {
+                        h.write_u64(def);
+                    }
+                }
+            }
+
+            definitions.push(h.finish());
+        }
+
+        let key = |kind: Kind, node: Node| {
+            let item = match node {
+                Node::Term(idx) => hashes.term(idx),
+                Node::Theorem(idx) => hashes.theorem(idx),
+            };
+
+            let item = item.unwrap_or_default();
+
+            let mut h = Fnv::new();
+            h.write_u64(kind as u64);
+            h.write_u64(item.statement);
+            h.write_u64(item.proof.unwrap_or(0));
+
+            for &dep in graph.dependencies(node) {
+                if let Node::Term(idx) = dep {
+                    if let Some(&def) = definitions.get(idx as usize) {
+                        h.write_u64(def);
+                    }
+                }
+            }
+
+            h.finish()
+        };
+
+        Keys {
+            sorts,
+            terms: (0..mmb.num_terms())
+                .map(|idx| key(Kind::Term, Node::Term(idx)))
+                .collect(),
+            theorems: (0..mmb.num_theorems())
+                .map(|idx| key(Kind::Theorem, Node::Theorem(idx)))
+                .collect(),
+        }
+    }
+
+    /// Returns the key of the item with the given kind and index
+    pub fn get(&self, kind: Kind, idx: u64) -> Option<u64> {
+        let keys = match kind {
+            Kind::Sort => &self.sorts,
+            Kind::Term => &self.terms,
+            Kind::Theorem => &self.theorems,
+        };
+
+        keys.get(idx as usize).copied()
+    }
+}
+
+/// A cache of results per statement, such as the outcome of verifying it,
+/// that can be carried over between runs on different versions of a file.
+///
+/// Results are stored by the cache key of the item declared by the statement,
+/// see `Keys`. To persist a cache, store the pairs returned by
+/// `Cache::iter` and rebuild it with `Cache::from_iter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cache<R> {
+    results: BTreeMap<u64, R>,
+}
+
+/// The outcome of updating a cache for a proof file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update<R> {
+    /// The cache for the new file, containing a result for every statement.
+    pub cache: Cache<R>,
+    /// The kind and index of the items whose statements were examined, as
+    /// opposed to taken from the previous cache.
+    pub examined: Vec<(Kind, u64)>,
+}
+
+impl<R> Default for Cache<R> {
+    fn default() -> Cache<R> {
+        Cache {
+            results: BTreeMap::new(),
+        }
+    }
+}
+
+impl<R> Cache<R> {
+    /// Create an empty cache.
+    pub fn new() -> Cache<R> {
+        Cache::default()
+    }
+
+    /// Returns the number of results in the cache.
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Returns whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Returns the result stored for the given key.
+    pub fn get(&self, key: u64) -> Option<&R> {
+        self.results.get(&key)
+    }
+
+    /// Store a result for the given key, returning the previous one.
+    pub fn insert(&mut self, key: u64, result: R) -> Option<R> {
+        self.results.insert(key, result)
+    }
+
+    /// Returns an iterator over the keys and results, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, &R)> {
+        self.results.iter().map(|(&key, result)| (key, result))
+    }
+
+    /// Build the cache for a proof file, reusing the results of this cache
+    /// and calling `examine` for every statement without one.
+    ///
+    /// Statements are visited in the order of the proof section, so `examine`
+    /// sees the dependencies of a statement before the statement itself.
+    /// Statements past the first statement that cannot be parsed are skipped.
+    pub fn update<F>(&self, mmb: &Mmb, mut examine: F) -> Update<R>
+    where
+        R: Clone,
+        F: FnMut(Kind, u64, &StatementEntry) -> R,
+    {
+        let keys = Keys::new(mmb);
+
+        let mut update = Update {
+            cache: Cache::new(),
+            examined: Vec::new(),
+        };
+
+        for (kind, idx, statement) in mmb.declarations() {
+            let key = match keys.get(kind, idx) {
+                Some(key) => key,
+                None => continue,
+            };
+
+            let result = match self.get(key) {
+                Some(result) => result.clone(),
+                None => {
+                    update.examined.push((kind, idx));
+                    examine(kind, idx, &statement)
+                }
+            };
+
+            update.cache.insert(key, result);
+        }
+
+        update
+    }
+}
+
+impl<R> core::iter::FromIterator<(u64, R)> for Cache<R> {
+    fn from_iter<I: IntoIterator<Item = (u64, R)>>(iter: I) -> Cache<R> {
+        Cache {
+            results: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<R> Extend<(u64, R)> for Cache<R> {
+    fn extend<I: IntoIterator<Item = (u64, R)>>(&mut self, iter: I) {
+        self.results.extend(iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode::{Proof, Unify};
+    use crate::testing::{self, Builder};
+
+    /// Build a file with the sort `wff`, and optionally a sort in front of it
+    /// and a term and an axiom inserted in front of the others.
+    fn file(sort_before: bool, inserted: bool) -> Vec<u8> {
+        let mut builder = Builder::new();
+
+        if sort_before {
+            builder.sort("nat");
+        }
+
+        let wff = builder.sort("wff");
+        let binder = (wff as u64) << 56;
+
+        if inserted {
+            builder.term("not", wff, &[binder]);
+            builder.axiom(
+                "ax0",
+                &[binder, binder],
+                testing::unify(&[(Unify::Ref, 0)]),
+                Vec::new(),
+            );
+        }
+
+        builder.term("im", wff, &[binder, binder]);
+        let ax = builder.axiom(
+            "ax",
+            &[binder],
+            testing::unify(&[(Unify::Ref, 0)]),
+            Vec::new(),
+        );
+        builder.theorem(
+            "th",
+            &[binder],
+            testing::unify(&[(Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Ref, 0), (Proof::Thm, ax)]),
+        );
+
+        builder.build()
+    }
+
+    fn keys(file: &[u8]) -> Keys {
+        Keys::new(&Mmb::from(file).unwrap())
+    }
+
+    #[test]
+    fn keys_survive_inserted_items() {
+        let old = keys(&file(false, false));
+        let new = keys(&file(false, true));
+
+        assert_eq!(new.get(Kind::Sort, 0), old.get(Kind::Sort, 0));
+        assert_eq!(new.get(Kind::Term, 1), old.get(Kind::Term, 0));
+        assert_eq!(new.get(Kind::Theorem, 1), old.get(Kind::Theorem, 0));
+        assert_eq!(new.get(Kind::Theorem, 2), old.get(Kind::Theorem, 1));
+        assert_ne!(new.get(Kind::Term, 0), old.get(Kind::Term, 0));
+    }
+
+    #[test]
+    fn keys_change_with_sort_indices() {
+        let old = keys(&file(false, false));
+        let new = keys(&file(true, false));
+
+        assert_ne!(new.get(Kind::Sort, 1), old.get(Kind::Sort, 0));
+        assert_ne!(new.get(Kind::Term, 0), old.get(Kind::Term, 0));
+    }
+
+    #[test]
+    fn update_reuses_results() {
+        let old = file(false, false);
+        let new = file(false, true);
+
+        let first = Cache::new().update(&Mmb::from(&old).unwrap(), |kind, idx, _| (kind, idx));
+        assert_eq!(first.examined.len(), 4);
+
+        let second = first
+            .cache
+            .update(&Mmb::from(&new).unwrap(), |kind, idx, _| (kind, idx));
+
+        assert_eq!(second.examined, [(Kind::Term, 0), (Kind::Theorem, 0)]);
+        assert_eq!(second.cache.len(), 6);
+    }
+
+    /// Add a definition `d1` with the given body, a definition `d2` using
+    /// `d1` and a theorem `th` using `d2` to `testing::logic`.
+    fn nested(body: &[(Proof, u32)]) -> Vec<u8> {
+        let mut builder = testing::logic();
+        let (not, ax) = (1, 0);
+
+        let d1 = builder.def(
+            "d1",
+            0,
+            &[0],
+            testing::unify(&[(Unify::Term, not), (Unify::Ref, 0)]),
+            testing::proof(body),
+        );
+        let d2 = builder.def(
+            "d2",
+            0,
+            &[0],
+            testing::unify(&[(Unify::Term, d1), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Term, d1)]),
+        );
+        builder.theorem(
+            "th",
+            &[0],
+            testing::unify(&[(Unify::Term, d2), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Term, d2), (Proof::Thm, ax)]),
+        );
+
+        builder.build()
+    }
+
+    #[test]
+    fn nested_definitions_are_part_of_keys() {
+        let old = nested(&[(Proof::Ref, 0), (Proof::Term, 1)]);
+        let new = nested(&[(Proof::Ref, 0), (Proof::Term, 1), (Proof::Term, 1)]);
+
+        let first = Cache::new().update(&Mmb::from(&old).unwrap(), |kind, idx, _| (kind, idx));
+        let second = first
+            .cache
+            .update(&Mmb::from(&new).unwrap(), |kind, idx, _| (kind, idx));
+
+        // `th` only mentions `d2`, but its proof may unfold `d2` and then `d1`.
+        assert_eq!(
+            second.examined,
+            [(Kind::Term, 2), (Kind::Term, 3), (Kind::Theorem, 1)]
+        );
+    }
+}
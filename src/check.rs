@@ -0,0 +1,520 @@
+use alloc::vec::Vec;
+
+use crate::index::name_table::{Kind, NameTable, NameTableSection};
+use crate::opcode::Statement;
+use crate::parser;
+use crate::statement::{declared_kind, MalformedStatement};
+use crate::Mmb;
+
+/// An inconsistency between the name table and the rest of the proof file.
+///
+/// Each variant identifies the offending entry by its kind and its index
+/// within the subsection of the name table of that kind.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NameTableError {
+    /// The entry is missing from the name table.
+    MissingEntry { kind: Kind, idx: u64 },
+    /// The name of the entry does not lie inside the file, or is not
+    /// nul-terminated before the end of the file.
+    NameOutOfBounds { kind: Kind, idx: u64, name_ptr: u64 },
+    /// The name of the entry is not valid UTF-8.
+    InvalidUtf8 { kind: Kind, idx: u64 },
+    /// The name of the entry has already been used by the entry `first` of the
+    /// same kind.
+    DuplicateName { kind: Kind, idx: u64, first: u64 },
+    /// The pointer of the entry does not point to the start of a statement in
+    /// the proof section.
+    NotAStatement { kind: Kind, idx: u64, ptr: u64 },
+    /// The pointer of the entry points to a statement of the wrong kind.
+    KindMismatch {
+        kind: Kind,
+        idx: u64,
+        ptr: u64,
+        statement: Statement,
+    },
+}
+
+/// A divergence between the statement stream and the sort, term and theorem
+/// tables.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatementError {
+    /// The statement stream could not be parsed at the given offset.
+    Malformed { offset: usize },
+    /// The statement at the given offset declares an item for which there is
+    /// no entry left in the table of its kind.
+    Extra { kind: Kind, offset: usize },
+    /// The entry `idx` of the table of the given kind is not declared by any
+    /// statement.
+    Missing { kind: Kind, idx: u64 },
+    /// The entry `idx` of the term table could not be parsed.
+    InvalidTerm { idx: u64 },
+    /// The term statement at the given offset does not fit the entry `idx` of
+    /// the term table, because it has no definition body although the entry is
+    /// marked as a definition, or it declares a local definition although the
+    /// entry is not marked as one.
+    DefinitionMismatch { idx: u64, offset: usize },
+    /// The entry `idx` of the given kind in the name table does not point to
+    /// the statement at the given offset, which declares the item.
+    NameMismatch { kind: Kind, idx: u64, offset: usize },
+}
+
+/// Check that the name table is consistent with the proof file.
+///
+/// This verifies that every entry of the name table points to the start of a
+/// statement of the matching kind in the proof section, that all names lie
+/// inside the file, and that names are valid UTF-8 and unique per kind. The
+/// first inconsistency that is found is returned as an error.
+pub fn name_table(mmb: &Mmb, names: &NameTable) -> Result<(), NameTableError> {
+    let base = mmb.proofs_ptr() as u64;
+
+    let statements: Vec<_> = mmb
+        .statements()
+        .map(|statement| (base + statement.offset as u64, statement.kind))
+        .collect();
+
+    let sections = [
+        (Kind::Sort, mmb.num_sorts() as u64),
+        (Kind::Term, mmb.num_terms() as u64),
+        (Kind::Theorem, mmb.num_theorems() as u64),
+    ];
+
+    for &(kind, num) in &sections {
+        name_table_section(&names.section(kind), kind, num, &statements)?;
+    }
+
+    Ok(())
+}
+
+/// Check that the statement stream declares the items of the sort, term and
+/// theorem tables in order.
+///
+/// The n-th statement declaring an item of some kind corresponds to the n-th
+/// entry of the table of that kind, where local definitions and local theorems
+/// are part of the term and theorem tables respectively. Whether a term is a
+/// definition is decided by the def bit of its term table entry: definitions
+/// must have a definition body, and only definitions may be declared by local
+/// definition statements. If the file has a name table, its pointers must point
+/// to the corresponding statements. The first divergence that is found is
+/// returned as an error.
+pub fn statements(mmb: &Mmb) -> Result<(), StatementError> {
+    let names = mmb.index().and_then(|index| index.name_table());
+    let base = mmb.proofs_ptr() as u64;
+
+    let lengths = [
+        mmb.num_sorts() as u64,
+        mmb.num_terms() as u64,
+        mmb.num_theorems() as u64,
+    ];
+    let mut counts = [0u64; 3];
+
+    let mut iter = mmb.statements();
+
+    for statement in &mut iter {
+        let offset = statement.offset;
+
+        let kind = match declared_kind(statement.kind) {
+            Some(kind) => kind,
+            None => return Err(StatementError::Malformed { offset }),
+        };
+
+        let idx = counts[kind as usize];
+
+        if idx >= lengths[kind as usize] {
+            return Err(StatementError::Extra { kind, offset });
+        }
+
+        if kind == Kind::Term {
+            let term = mmb
+                .term(idx as u32)
+                .ok_or(StatementError::InvalidTerm { idx })?;
+
+            let is_def = term.sort & 0x80 != 0;
+            let mismatch = if is_def {
+                statement.proof.is_empty()
+            } else {
+                statement.kind == Statement::LocalDef
+            };
+
+            if mismatch {
+                return Err(StatementError::DefinitionMismatch { idx, offset });
+            }
+        }
+
+        if let Some(name) = names.as_ref().and_then(|n| n.section(kind).get(idx)) {
+            if name.ptr != base + offset as u64 {
+                return Err(StatementError::NameMismatch { kind, idx, offset });
+            }
+        }
+
+        counts[kind as usize] += 1;
+    }
+
+    if let Some(MalformedStatement { offset }) = iter.error() {
+        return Err(StatementError::Malformed { offset });
+    }
+
+    for &kind in &[Kind::Sort, Kind::Term, Kind::Theorem] {
+        let idx = counts[kind as usize];
+
+        if idx < lengths[kind as usize] {
+            return Err(StatementError::Missing { kind, idx });
+        }
+    }
+
+    Ok(())
+}
+
+fn name_table_section(
+    section: &NameTableSection,
+    kind: Kind,
+    num: u64,
+    statements: &[(u64, Statement)],
+) -> Result<(), NameTableError> {
+    let mut seen = Vec::new();
+
+    for idx in 0..num {
+        let (_, (ptr, name_ptr)) = parser::seek_raw_name_entry(section.entries, idx)
+            .map_err(|_| NameTableError::MissingEntry { kind, idx })?;
+
+        let name = section
+            .get(idx)
+            .map(|name| name.name)
+            .filter(|name| {
+                let end = name_ptr + name.len() as u64;
+                matches!(section.file.seek(end), Ok(([0, ..], _)))
+            })
+            .ok_or(NameTableError::NameOutOfBounds {
+                kind,
+                idx,
+                name_ptr,
+            })?;
+
+        if core::str::from_utf8(name).is_err() {
+            return Err(NameTableError::InvalidUtf8 { kind, idx });
+        }
+
+        let statement = statements
+            .binary_search_by_key(&ptr, |&(offset, _)| offset)
+            .map(|i| statements[i].1)
+            .map_err(|_| NameTableError::NotAStatement { kind, idx, ptr })?;
+
+        if declared_kind(statement) != Some(kind) {
+            return Err(NameTableError::KindMismatch {
+                kind,
+                idx,
+                ptr,
+                statement,
+            });
+        }
+
+        seen.push((name, idx));
+    }
+
+    seen.sort_unstable();
+
+    for pair in seen.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            return Err(NameTableError::DuplicateName {
+                kind,
+                idx: pair[1].1,
+                first: pair[0].1,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::TryInto;
+
+    use super::*;
+    use crate::opcode::{Proof, Unify};
+    use crate::testing;
+
+    /// The positions of the entries of `testing::logic` in the name table.
+    const WFF: usize = 0;
+    const IM: usize = 1;
+    const NOT: usize = 2;
+    const AX: usize = 3;
+
+    fn check_names(file: &[u8]) -> Result<(), NameTableError> {
+        let mmb = Mmb::from(file).unwrap();
+        let names = mmb.index().unwrap().name_table().unwrap();
+
+        name_table(&mmb, &names)
+    }
+
+    fn read_u64(file: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(file[at..at + 8].try_into().unwrap())
+    }
+
+    fn write_u64(file: &mut [u8], at: usize, value: u64) {
+        file[at..at + 8].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Returns the offset of the name of the entry at the given position.
+    fn name_ptr(file: &[u8], position: usize) -> usize {
+        read_u64(file, testing::name_entry(file, position) + 8) as usize
+    }
+
+    #[test]
+    fn consistent_name_tables() {
+        assert_eq!(check_names(&testing::logic().build()), Ok(()));
+    }
+
+    #[test]
+    fn missing_entries() {
+        let file = testing::logic().build();
+        let mmb = Mmb::from(&file).unwrap();
+
+        // A name table with one entry less than there are items.
+        let entries = testing::name_entry(&file, 0)..testing::name_entry(&file, AX);
+        let names = NameTable::new(1, 2, 1, parser::Window::new(&file, 0), &file[entries], None);
+
+        assert_eq!(
+            name_table(&mmb, &names),
+            Err(NameTableError::MissingEntry {
+                kind: Kind::Theorem,
+                idx: 0
+            })
+        );
+    }
+
+    #[test]
+    fn names_out_of_bounds() {
+        let mut file = testing::logic().build();
+        let entry = testing::name_entry(&file, NOT);
+        write_u64(&mut file, entry + 8, u64::MAX);
+
+        assert_eq!(
+            check_names(&file),
+            Err(NameTableError::NameOutOfBounds {
+                kind: Kind::Term,
+                idx: 1,
+                name_ptr: u64::MAX
+            })
+        );
+    }
+
+    #[test]
+    fn names_without_nul() {
+        let mut file = testing::logic().build();
+
+        // The name of the last entry ends the file, so it loses its nul.
+        let name_ptr = name_ptr(&file, AX);
+        let last = file.len() - 1;
+        file[last] = b'x';
+
+        assert_eq!(
+            check_names(&file),
+            Err(NameTableError::NameOutOfBounds {
+                kind: Kind::Theorem,
+                idx: 0,
+                name_ptr: name_ptr as u64
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut file = testing::logic().build();
+        let name_ptr = name_ptr(&file, WFF);
+        file[name_ptr] = 0xFF;
+
+        assert_eq!(
+            check_names(&file),
+            Err(NameTableError::InvalidUtf8 {
+                kind: Kind::Sort,
+                idx: 0
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let mut builder = testing::logic();
+        builder.term("not", 0, &[]);
+        builder.term("im", 0, &[]);
+
+        assert_eq!(
+            check_names(&builder.build()),
+            Err(NameTableError::DuplicateName {
+                kind: Kind::Term,
+                idx: 3,
+                first: 0
+            })
+        );
+    }
+
+    #[test]
+    fn pointers_between_statements() {
+        let mut file = testing::logic().build();
+        let entry = testing::name_entry(&file, IM);
+        let ptr = read_u64(&file, entry) + 1;
+        write_u64(&mut file, entry, ptr);
+
+        assert_eq!(
+            check_names(&file),
+            Err(NameTableError::NotAStatement {
+                kind: Kind::Term,
+                idx: 0,
+                ptr
+            })
+        );
+    }
+
+    #[test]
+    fn pointers_to_other_kinds() {
+        let mut file = testing::logic().build();
+        let ptr = read_u64(&file, testing::name_entry(&file, WFF));
+        let entry = testing::name_entry(&file, AX);
+        write_u64(&mut file, entry, ptr);
+
+        assert_eq!(
+            check_names(&file),
+            Err(NameTableError::KindMismatch {
+                kind: Kind::Theorem,
+                idx: 0,
+                ptr,
+                statement: Statement::Sort
+            })
+        );
+    }
+
+    /// The offsets of the statements of `testing::logic` in the proof section.
+    const IM_OFFSET: usize = 5;
+    const NOT_OFFSET: usize = 10;
+    const AX_OFFSET: usize = 15;
+
+    fn check_statements(file: &[u8]) -> Result<(), StatementError> {
+        statements(&Mmb::from(file).unwrap())
+    }
+
+    fn proofs_ptr(file: &[u8]) -> usize {
+        u32::from_le_bytes(file[24..28].try_into().unwrap()) as usize
+    }
+
+    fn term_entry(file: &[u8], idx: usize) -> usize {
+        u32::from_le_bytes(file[16..20].try_into().unwrap()) as usize + 8 * idx
+    }
+
+    #[test]
+    fn consistent_statements() {
+        let mut builder = testing::logic();
+        builder.def(
+            "id",
+            0,
+            &[0],
+            testing::unify(&[(Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0)]),
+        );
+
+        assert_eq!(check_statements(&builder.build()), Ok(()));
+    }
+
+    #[test]
+    fn malformed_statements() {
+        let mut file = testing::logic().build();
+        let not = proofs_ptr(&file) + NOT_OFFSET;
+        file[not + 1..not + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert_eq!(
+            check_statements(&file),
+            Err(StatementError::Malformed { offset: NOT_OFFSET })
+        );
+    }
+
+    #[test]
+    fn extra_statements() {
+        let mut file = testing::logic().build();
+        file[12..16].copy_from_slice(&0u32.to_le_bytes());
+
+        assert_eq!(
+            check_statements(&file),
+            Err(StatementError::Extra {
+                kind: Kind::Theorem,
+                offset: AX_OFFSET
+            })
+        );
+    }
+
+    #[test]
+    fn missing_statements() {
+        let mut file = testing::logic().build();
+
+        // End the statement stream before the axiom.
+        let ax = proofs_ptr(&file) + AX_OFFSET;
+        file[ax] = 0;
+
+        assert_eq!(
+            check_statements(&file),
+            Err(StatementError::Missing {
+                kind: Kind::Theorem,
+                idx: 0
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_terms() {
+        let mut file = testing::logic().build();
+        let not = term_entry(&file, 1);
+        file[not + 4..not + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        assert_eq!(
+            check_statements(&file),
+            Err(StatementError::InvalidTerm { idx: 1 })
+        );
+    }
+
+    #[test]
+    fn definitions_without_body() {
+        let mut file = testing::logic().build();
+        let im = term_entry(&file, 0);
+        file[im + 2] |= 0x80;
+
+        assert_eq!(
+            check_statements(&file),
+            Err(StatementError::DefinitionMismatch {
+                idx: 0,
+                offset: IM_OFFSET
+            })
+        );
+    }
+
+    #[test]
+    fn local_definitions_of_terms() {
+        let mut file = testing::logic().build();
+        let not = proofs_ptr(&file) + NOT_OFFSET;
+        file[not] = Statement::LocalDef as u8 | 0xC0;
+
+        assert_eq!(
+            check_statements(&file),
+            Err(StatementError::DefinitionMismatch {
+                idx: 1,
+                offset: NOT_OFFSET
+            })
+        );
+    }
+
+    #[test]
+    fn swapped_names() {
+        let mut file = testing::logic().build();
+        let im = testing::name_entry(&file, IM);
+        let not = testing::name_entry(&file, NOT);
+        let (im_ptr, not_ptr) = (read_u64(&file, im), read_u64(&file, not));
+        write_u64(&mut file, im, not_ptr);
+        write_u64(&mut file, not, im_ptr);
+
+        assert_eq!(
+            check_statements(&file),
+            Err(StatementError::NameMismatch {
+                kind: Kind::Term,
+                idx: 0,
+                offset: IM_OFFSET
+            })
+        );
+    }
+}
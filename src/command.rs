@@ -0,0 +1,62 @@
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+
+use crate::opcode::{Command, Proof, Unify};
+use crate::parser;
+
+/// An iterator over the commands of a unify or proof stream.
+///
+/// Each command is returned together with its offset. The iterator stops after
+/// the end command of the stream, or at the first command that cannot be
+/// parsed.
+pub struct CommandIter<'a, T> {
+    input: &'a [u8],
+    offset: usize,
+    done: bool,
+    marker: PhantomData<T>,
+}
+
+/// An iterator over the commands of a proof stream.
+pub type ProofCommandIter<'a> = CommandIter<'a, Proof>;
+
+/// An iterator over the commands of a unify stream.
+pub type UnifyCommandIter<'a> = CommandIter<'a, Unify>;
+
+impl<'a, T> CommandIter<'a, T> {
+    /// Build an iterator over the stream starting at `input`, where `offset` is
+    /// the offset of the first command that is reported by the iterator.
+    pub fn new(input: &'a [u8], offset: usize) -> CommandIter<'a, T> {
+        CommandIter {
+            input,
+            offset,
+            done: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the offset of the next command.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a, T: TryFrom<u8>> Iterator for CommandIter<'a, T> {
+    type Item = (usize, Command<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let opcode = *self.input.first()?;
+        let (left, command) = parser::parse_opcode(self.input).ok()?;
+
+        let offset = self.offset;
+
+        self.offset += self.input.len() - left.len();
+        self.input = left;
+        self.done = opcode & 0x3F == 0x00;
+
+        Some((offset, command))
+    }
+}
@@ -0,0 +1,560 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::hash::{Hashes, ItemHash};
+use crate::index::{Kind, NameTable};
+use crate::opcode::{Command, Proof, Unify};
+use crate::statement::StatementEntry;
+use crate::Mmb;
+
+/// How the sorts, terms and theorems of two files are matched up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Matching {
+    /// Items are matched by their name in the name table.
+    ByName,
+    /// Items are matched by their index in the table of their kind.
+    ByPosition,
+}
+
+/// A difference between two proof files.
+///
+/// Items are identified by their kind and their index in the table of that
+/// kind, where `old` refers to the old file and `new` to the new file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The item of the new file has no counterpart in the old file.
+    Added { kind: Kind, new: u64 },
+    /// The item of the old file has no counterpart in the new file.
+    Removed { kind: Kind, old: u64 },
+    /// The item has a different name in the new file.
+    Renamed { kind: Kind, old: u64, new: u64 },
+    /// The statement of the item changed, that is the modifiers of a sort, or
+    /// the binders, return type or unify stream of a term or theorem.
+    Statement { kind: Kind, old: u64, new: u64 },
+    /// The proof stream of the item changed, which includes the body of a
+    /// definition.
+    Proof { kind: Kind, old: u64, new: u64 },
+}
+
+/// Compare two proof files structurally.
+///
+/// Matched items are compared by their decoded statements and proof streams,
+/// where references to sorts, terms and theorems are translated from the old
+/// to the new file, so that inserting an item does not change every item after
+/// it. When matching by name, a removed and an added item with identical
+/// statements and proofs are reported as renamed.
+///
+/// Renames are resolved before any other items are compared, so an item that
+/// references a renamed item is not reported as changed, and chains of items
+/// referencing each other are detected as renamed together. Only items with
+/// equal content hashes, as computed by `hash::Hashes`, are considered as
+/// renames of each other, so an item that is renamed while a sort before it
+/// is inserted or removed is reported as removed and added.
+///
+/// Returns `None` when matching by name and either file has no name table.
+pub fn diff(old: &Mmb, new: &Mmb, matching: Matching) -> Option<Vec<Change>> {
+    let old = File::new(old);
+    let new = File::new(new);
+
+    let mut maps = [Vec::new(), Vec::new(), Vec::new()];
+
+    for &kind in &KINDS {
+        maps[kind as usize] = match matching {
+            Matching::ByName => match_by_name(old.names.as_ref()?, new.names.as_ref()?, kind),
+            Matching::ByPosition => {
+                let new_len = new.len(kind);
+
+                (0..old.len(kind))
+                    .map(|idx| Some(idx).filter(|&idx| idx < new_len))
+                    .collect()
+            }
+        };
+    }
+
+    let renamed = match matching {
+        Matching::ByName => find_renames(&old, &new, &mut maps),
+        Matching::ByPosition => Default::default(),
+    };
+
+    let cmp = Comparison {
+        old: &old,
+        new: &new,
+        maps: &maps,
+    };
+
+    let mut changes = Vec::new();
+
+    for &kind in &KINDS {
+        let mut removed = Vec::new();
+        let mut matched = vec![false; new.len(kind) as usize];
+
+        for (old_idx, &new_idx) in (0..).zip(&maps[kind as usize]) {
+            let new_idx = match new_idx {
+                Some(new_idx) => new_idx,
+                None => {
+                    removed.push(old_idx);
+                    continue;
+                }
+            };
+
+            matched[new_idx as usize] = true;
+
+            if matching == Matching::ByPosition
+                && old.name(kind, old_idx) != new.name(kind, new_idx)
+            {
+                changes.push(Change::Renamed {
+                    kind,
+                    old: old_idx,
+                    new: new_idx,
+                });
+            }
+
+            if !cmp.same_statement(kind, old_idx, new_idx) {
+                changes.push(Change::Statement {
+                    kind,
+                    old: old_idx,
+                    new: new_idx,
+                });
+            }
+
+            if !cmp.same_proof(kind, old_idx, new_idx) {
+                changes.push(Change::Proof {
+                    kind,
+                    old: old_idx,
+                    new: new_idx,
+                });
+            }
+        }
+
+        changes.extend(
+            renamed[kind as usize]
+                .iter()
+                .map(|&(old, new)| Change::Renamed { kind, old, new }),
+        );
+
+        changes.extend(removed.into_iter().map(|old| Change::Removed { kind, old }));
+
+        changes.extend(
+            (0..new.len(kind))
+                .filter(|&idx| !matched[idx as usize])
+                .map(|new| Change::Added { kind, new }),
+        );
+    }
+
+    Some(changes)
+}
+
+const KINDS: [Kind; 3] = [Kind::Sort, Kind::Term, Kind::Theorem];
+
+/// Match the unmatched items of the old file to unmatched items of the new file
+/// with identical statements and proofs, add them to the maps, and return them
+/// sorted by kind and old index.
+///
+/// Matching an item can make the items referencing it identical, so this is
+/// repeated until no more items are matched.
+fn find_renames(old: &File, new: &File, maps: &mut [Vec<Option<u64>>; 3]) -> [Vec<(u64, u64)>; 3] {
+    let old_hashes = Hashes::new(old.mmb);
+    let new_hashes = Hashes::new(new.mmb);
+
+    let mut removed: [Vec<u64>; 3] = Default::default();
+    let mut added: [BTreeMap<ItemHash, Vec<u64>>; 3] = Default::default();
+
+    for &kind in &KINDS {
+        let map = &maps[kind as usize];
+        let mut matched = vec![false; new.len(kind) as usize];
+
+        for (old_idx, &new_idx) in (0..).zip(map) {
+            match new_idx {
+                Some(new_idx) => matched[new_idx as usize] = true,
+                None => removed[kind as usize].push(old_idx),
+            }
+        }
+
+        for new_idx in (0..new.len(kind)).filter(|&idx| !matched[idx as usize]) {
+            added[kind as usize]
+                .entry(new.content_hash(&new_hashes, kind, new_idx))
+                .or_default()
+                .push(new_idx);
+        }
+    }
+
+    let mut renamed: [Vec<(u64, u64)>; 3] = Default::default();
+
+    loop {
+        let mut progress = false;
+
+        for &kind in &KINDS {
+            let k = kind as usize;
+
+            for &old_idx in &removed[k] {
+                let hash = old.content_hash(&old_hashes, kind, old_idx);
+
+                let candidates = match added[k].get_mut(&hash) {
+                    Some(candidates) => candidates,
+                    None => continue,
+                };
+
+                let cmp = Comparison { old, new, maps };
+
+                let found = candidates.iter().position(|&new_idx| {
+                    cmp.same_statement(kind, old_idx, new_idx)
+                        && cmp.same_proof(kind, old_idx, new_idx)
+                });
+
+                if let Some(i) = found {
+                    let new_idx = candidates.remove(i);
+                    maps[k][old_idx as usize] = Some(new_idx);
+                    renamed[k].push((old_idx, new_idx));
+                    progress = true;
+                }
+            }
+
+            removed[k].retain(|&old_idx| maps[k][old_idx as usize].is_none());
+        }
+
+        if !progress {
+            break;
+        }
+    }
+
+    for renamed in &mut renamed {
+        renamed.sort_unstable();
+    }
+
+    renamed
+}
+
+/// The parts of a proof file that are needed for comparing items.
+struct File<'a> {
+    mmb: &'a Mmb<'a>,
+    names: Option<NameTable<'a>>,
+    /// The statements declaring the terms and theorems, indexed by kind and
+    /// item.
+    statements: [Vec<Option<StatementEntry<'a>>>; 3],
+}
+
+impl<'a> File<'a> {
+    fn new(mmb: &'a Mmb<'a>) -> File<'a> {
+        let mut statements = [
+            vec![None; mmb.num_sorts() as usize],
+            vec![None; mmb.num_terms() as usize],
+            vec![None; mmb.num_theorems() as usize],
+        ];
+
+        for (kind, idx, statement) in mmb.declarations() {
+            if let Some(slot) = statements[kind as usize].get_mut(idx as usize) {
+                *slot = Some(statement);
+            }
+        }
+
+        File {
+            mmb,
+            names: mmb.index().and_then(|index| index.name_table()),
+            statements,
+        }
+    }
+
+    fn len(&self, kind: Kind) -> u64 {
+        match kind {
+            Kind::Sort => self.mmb.num_sorts() as u64,
+            Kind::Term => self.mmb.num_terms() as u64,
+            Kind::Theorem => self.mmb.num_theorems() as u64,
+        }
+    }
+
+    fn name(&self, kind: Kind, idx: u64) -> Option<&'a [u8]> {
+        let name = self.names.as_ref()?.section(kind).get(idx)?;
+
+        Some(name.name)
+    }
+
+    /// Returns the content hash of an item, which for sorts are their
+    /// modifiers.
+    fn content_hash(&self, hashes: &Hashes, kind: Kind, idx: u64) -> ItemHash {
+        let hash = match kind {
+            Kind::Sort => self.mmb.sorts().get(idx as usize).map(|&sort| ItemHash {
+                statement: sort as u64,
+                proof: None,
+            }),
+            Kind::Term => hashes.term(idx as u32),
+            Kind::Theorem => hashes.theorem(idx as u32),
+        };
+
+        hash.unwrap_or_default()
+    }
+
+    fn proof(&self, kind: Kind, idx: u64) -> Option<&StatementEntry<'a>> {
+        self.statements[kind as usize].get(idx as usize)?.as_ref()
+    }
+}
+
+fn match_by_name(old: &NameTable, new: &NameTable, kind: Kind) -> Vec<Option<u64>> {
+    let new: BTreeMap<_, _> = new
+        .section(kind)
+        .iter()
+        .zip(0..)
+        .map(|(name, idx)| (name.name, idx))
+        .collect();
+
+    old.section(kind)
+        .iter()
+        .map(|name| new.get(name.name).copied())
+        .collect()
+}
+
+/// Compares items of two files, translating references from the old file to
+/// the new file.
+struct Comparison<'a, 'b> {
+    old: &'b File<'a>,
+    new: &'b File<'a>,
+    maps: &'b [Vec<Option<u64>>; 3],
+}
+
+impl<'a, 'b> Comparison<'a, 'b> {
+    /// Translate the index of an item of the old file to the new file, or
+    /// return a value that matches no item if it has no counterpart.
+    fn map(&self, kind: Kind, idx: u64) -> u64 {
+        self.maps[kind as usize]
+            .get(idx as usize)
+            .copied()
+            .flatten()
+            .unwrap_or(u64::MAX)
+    }
+
+    fn map_binder(&self, binder: u64) -> u64 {
+        let sort = (binder >> 56) & 0x7F;
+        let sort = self.map(Kind::Sort, sort) & 0x7F;
+
+        (binder & !(0x7F << 56)) | (sort << 56)
+    }
+
+    fn map_unify(&self, command: Command<Unify>) -> (Unify, u64) {
+        let operand = command.operand as u64;
+
+        let operand = match command.opcode {
+            Unify::Term | Unify::TermSave => self.map(Kind::Term, operand),
+            Unify::Dummy => self.map(Kind::Sort, operand),
+            _ => operand,
+        };
+
+        (command.opcode, operand)
+    }
+
+    fn map_proof(&self, command: Command<Proof>) -> (Proof, u64) {
+        let operand = command.operand as u64;
+
+        let operand = match command.opcode {
+            Proof::Term | Proof::TermSave => self.map(Kind::Term, operand),
+            Proof::Thm | Proof::ThmSave => self.map(Kind::Theorem, operand),
+            Proof::Dummy => self.map(Kind::Sort, operand),
+            _ => operand,
+        };
+
+        (command.opcode, operand)
+    }
+
+    fn same_statement(&self, kind: Kind, old: u64, new: u64) -> bool {
+        let (old_mmb, new_mmb) = (self.old.mmb, self.new.mmb);
+
+        match kind {
+            Kind::Sort => old_mmb.sorts().get(old as usize) == new_mmb.sorts().get(new as usize),
+            Kind::Term => match (old_mmb.term(old as u32), new_mmb.term(new as u32)) {
+                (Some(a), Some(b)) => {
+                    a.is_def() == b.is_def()
+                        && self.map(Kind::Sort, a.sort_idx() as u64) == b.sort_idx() as u64
+                        && self.map_binder(a.ret_ty) == b.ret_ty
+                        && a.binders().map(|x| self.map_binder(x)).eq(b.binders())
+                        && a.unify_commands()
+                            .map(|(_, c)| self.map_unify(c))
+                            .eq(b.unify_commands().map(plain))
+                }
+                (a, b) => a.is_none() && b.is_none(),
+            },
+            Kind::Theorem => match (old_mmb.theorem(old as u32), new_mmb.theorem(new as u32)) {
+                (Some(a), Some(b)) => {
+                    a.binders().map(|x| self.map_binder(x)).eq(b.binders())
+                        && a.unify_commands()
+                            .map(|(_, c)| self.map_unify(c))
+                            .eq(b.unify_commands().map(plain))
+                }
+                (a, b) => a.is_none() && b.is_none(),
+            },
+        }
+    }
+
+    fn same_proof(&self, kind: Kind, old: u64, new: u64) -> bool {
+        match (self.old.proof(kind, old), self.new.proof(kind, new)) {
+            (Some(a), Some(b)) => {
+                a.kind == b.kind
+                    && a.proof_commands()
+                        .map(|(_, c)| self.map_proof(c))
+                        .eq(b.proof_commands().map(plain))
+            }
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+}
+
+/// Strip the offset from a command and widen its operand.
+fn plain<T>((_, command): (usize, Command<T>)) -> (T, u64) {
+    (command.opcode, command.operand as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, Builder};
+
+    /// Build a file with the sort `wff`, the terms `im` and `not` and the
+    /// definition `dn` of double negation, with the given names for `not` and
+    /// `dn`, followed by an axiom stating `im a (not a)` and a theorem using
+    /// that axiom in its proof.
+    fn file(not: &'static str, dn: &'static str, ax: &'static str) -> Vec<u8> {
+        let mut builder = Builder::new();
+        let wff = builder.sort("wff");
+        let im = builder.term("im", wff, &[0, 0]);
+        let not = builder.term(not, wff, &[0]);
+
+        builder.def(
+            dn,
+            wff,
+            &[0],
+            testing::unify(&[(Unify::Term, not), (Unify::Term, not), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Term, not), (Proof::Term, not)]),
+        );
+
+        let unify = testing::unify(&[
+            (Unify::Term, im),
+            (Unify::Ref, 0),
+            (Unify::Term, not),
+            (Unify::Ref, 0),
+        ]);
+        let ax = builder.axiom(ax, &[0], unify.clone(), Vec::new());
+
+        builder.theorem(
+            "thm",
+            &[0],
+            unify,
+            testing::proof(&[
+                (Proof::Ref, 0),
+                (Proof::Ref, 0),
+                (Proof::Term, not),
+                (Proof::Term, im),
+                (Proof::Thm, ax),
+            ]),
+        );
+
+        builder.build()
+    }
+
+    fn changes(old: &[u8], new: &[u8], matching: Matching) -> Vec<Change> {
+        let old = Mmb::from(old).unwrap();
+        let new = Mmb::from(new).unwrap();
+
+        diff(&old, &new, matching).unwrap()
+    }
+
+    #[test]
+    fn identical_files_have_no_changes() {
+        let file = file("not", "dn", "ax");
+
+        assert_eq!(changes(&file, &file, Matching::ByName), vec![]);
+        assert_eq!(changes(&file, &file, Matching::ByPosition), vec![]);
+    }
+
+    #[test]
+    fn renaming_a_term_does_not_change_the_items_using_it() {
+        let old = file("not", "dn", "ax");
+        let new = file("neg", "dn", "ax");
+
+        assert_eq!(
+            changes(&old, &new, Matching::ByName),
+            vec![Change::Renamed {
+                kind: Kind::Term,
+                old: 1,
+                new: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn chains_of_renamed_items_are_detected() {
+        let old = file("not", "dn", "ax");
+        let new = file("neg", "dneg", "axiom");
+
+        assert_eq!(
+            changes(&old, &new, Matching::ByName),
+            vec![
+                Change::Renamed {
+                    kind: Kind::Term,
+                    old: 1,
+                    new: 1,
+                },
+                Change::Renamed {
+                    kind: Kind::Term,
+                    old: 2,
+                    new: 2,
+                },
+                Change::Renamed {
+                    kind: Kind::Theorem,
+                    old: 0,
+                    new: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn renames_are_reported_by_position() {
+        let old = file("not", "dn", "ax");
+        let new = file("neg", "dn", "ax");
+
+        assert_eq!(
+            changes(&old, &new, Matching::ByPosition),
+            vec![Change::Renamed {
+                kind: Kind::Term,
+                old: 1,
+                new: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn changed_items_are_not_renames() {
+        let old = file("not", "dn", "ax");
+
+        let mut builder = Builder::new();
+        let wff = builder.sort("wff");
+        builder.term("im", wff, &[0, 0]);
+        builder.term("neg", wff, &[0, 0]);
+
+        let new = builder.build();
+
+        assert_eq!(
+            changes(&old, &new, Matching::ByName),
+            vec![
+                Change::Removed {
+                    kind: Kind::Term,
+                    old: 1,
+                },
+                Change::Removed {
+                    kind: Kind::Term,
+                    old: 2,
+                },
+                Change::Added {
+                    kind: Kind::Term,
+                    new: 1,
+                },
+                Change::Removed {
+                    kind: Kind::Theorem,
+                    old: 0,
+                },
+                Change::Removed {
+                    kind: Kind::Theorem,
+                    old: 1,
+                },
+            ]
+        );
+    }
+}
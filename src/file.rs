@@ -0,0 +1,117 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::version::{Compatibility, FormatError};
+use crate::Mmb;
+
+/// A proof file loaded from disk.
+///
+/// With the `mmap` feature enabled, the file is memory-mapped, so that pages
+/// are only read from disk when they are accessed. If the file cannot be
+/// mapped, or the feature is disabled, the file is read into memory instead.
+pub struct MmbFile {
+    data: Data,
+}
+
+enum Data {
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+    Buffer(Vec<u8>),
+}
+
+impl MmbFile {
+    /// Open the proof file at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<MmbFile> {
+        let mut file = File::open(path)?;
+
+        #[cfg(feature = "mmap")]
+        {
+            // SAFETY: the mapping is only read through shared references. As
+            // with any memory-mapped file, the contents may change if the file
+            // is modified on disk while it is mapped.
+            if let Ok(map) = unsafe { memmap2::Mmap::map(&file) } {
+                return Ok(MmbFile {
+                    data: Data::Mapped(map),
+                });
+            }
+        }
+
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer)?;
+
+        Ok(MmbFile {
+            data: Data::Buffer(buffer),
+        })
+    }
+
+    /// Return the slice containing the entire file
+    pub fn bytes(&self) -> &[u8] {
+        match &self.data {
+            #[cfg(feature = "mmap")]
+            Data::Mapped(map) => map,
+            Data::Buffer(buffer) => buffer,
+        }
+    }
+
+    /// Return whether the file is memory-mapped rather than read into memory
+    pub fn is_mapped(&self) -> bool {
+        match self.data {
+            #[cfg(feature = "mmap")]
+            Data::Mapped(_) => true,
+            Data::Buffer(_) => false,
+        }
+    }
+
+    /// Build a `Mmb` struct borrowing from this file by parsing the file
+    /// header, accepting only the supported versions
+    pub fn mmb(&self) -> Option<Mmb<'_>> {
+        Mmb::from(self.bytes())
+    }
+
+    /// Build a `Mmb` struct borrowing from this file by parsing the file
+    /// header, accepting the versions allowed by the given compatibility policy
+    pub fn mmb_with_compatibility(
+        &self,
+        compatibility: Compatibility,
+    ) -> Result<Mmb<'_>, FormatError> {
+        Mmb::with_compatibility(self.bytes(), compatibility)
+    }
+}
+
+impl AsRef<[u8]> for MmbFile {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn newer_versions_are_opened_on_request() {
+        let mut bytes = testing::logic().build();
+        bytes[4] = 2;
+
+        let path = std::env::temp_dir().join(format!("mmb-parser-{}.mmb", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        let file = MmbFile::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let file = file.unwrap();
+
+        assert_eq!(file.bytes(), &bytes[..]);
+        assert!(file.mmb().is_none());
+        assert_eq!(
+            file.mmb_with_compatibility(Compatibility::Strict).err(),
+            Some(FormatError::UnsupportedVersion(2))
+        );
+
+        let mmb = file
+            .mmb_with_compatibility(Compatibility::AcceptNewer)
+            .unwrap();
+        assert_eq!(mmb.version(), 2);
+        assert_eq!(mmb.num_sorts(), 1);
+    }
+}
@@ -0,0 +1,787 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::index::{Kind, NameTable};
+use crate::opcode::{Command, Proof, Statement, Unify};
+use crate::Mmb;
+
+/// A node of the dependency graph.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Node {
+    Term(u32),
+    Theorem(u32),
+}
+
+/// The dependency graph between the terms and theorems of a proof file.
+///
+/// A theorem depends on the theorems invoked by its proof and on the terms
+/// mentioned by its statement and its proof. A term depends on the terms
+/// mentioned by its definition, if it is one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyGraph {
+    terms: Vec<Vec<Node>>,
+    theorems: Vec<Vec<Node>>,
+    /// The kind of the statement declaring each theorem, or `None` if the
+    /// theorem has no statement with a proof stream.
+    statements: Vec<Option<Statement>>,
+    /// The offset of the statement declaring each theorem relative to the
+    /// start of the proof section, or `None` if no statement declares it.
+    offsets: Vec<Option<usize>>,
+}
+
+/// The axioms and unproven theorems a theorem transitively depends on.
+///
+/// The proof file format has no dedicated command for unfinished proofs, so
+/// the only escapes that can be detected are theorems without a proof stream,
+/// such as theorems whose statement is missing from the proof section.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AxiomFootprint {
+    /// The indices of the axioms, sorted and without duplicates.
+    pub axioms: Vec<u32>,
+    /// The indices of the theorems that are neither axioms nor have a proof
+    /// stream, sorted and without duplicates.
+    pub unproven: Vec<u32>,
+}
+
+/// A theorem that is not proven in the file, because it has no proof stream or
+/// transitively depends on a theorem without one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Unproven {
+    /// The index of the theorem.
+    pub theorem: u32,
+    /// The offset of the statement declaring the theorem relative to the start
+    /// of the proof section, or `None` if no statement declares it.
+    pub offset: Option<usize>,
+    /// The indices of the theorems without a proof stream the theorem depends
+    /// on, including the theorem itself if it has none, sorted and without
+    /// duplicates.
+    pub missing: Vec<u32>,
+}
+
+impl DependencyGraph {
+    /// Build the dependency graph by walking the unify streams of all terms
+    /// and theorems and the proof streams of all statements.
+    ///
+    /// Commands past the first command that cannot be parsed are ignored, as
+    /// are operands that are out of range.
+    pub fn new(mmb: &Mmb) -> DependencyGraph {
+        let num_terms = mmb.num_terms();
+        let num_theorems = mmb.num_theorems();
+
+        let mut graph = DependencyGraph {
+            terms: vec![Vec::new(); num_terms as usize],
+            theorems: vec![Vec::new(); num_theorems as usize],
+            statements: vec![None; num_theorems as usize],
+            offsets: vec![None; num_theorems as usize],
+        };
+
+        let term = |idx: u32| Some(Node::Term(idx)).filter(|_| idx < num_terms);
+        let theorem = |idx: u32| Some(Node::Theorem(idx)).filter(|_| idx < num_theorems);
+
+        let unify = |command: Command<Unify>| match command.opcode {
+            Unify::Term | Unify::TermSave => term(command.operand),
+            _ => None,
+        };
+
+        for (idx, deps) in graph.terms.iter_mut().enumerate() {
+            if let Some(entry) = mmb.term(idx as u32) {
+                deps.extend(entry.unify_commands().filter_map(|(_, c)| unify(c)));
+            }
+        }
+
+        for (idx, deps) in graph.theorems.iter_mut().enumerate() {
+            if let Some(entry) = mmb.theorem(idx as u32) {
+                deps.extend(entry.unify_commands().filter_map(|(_, c)| unify(c)));
+            }
+        }
+
+        for (kind, idx, statement) in mmb.declarations() {
+            let deps = match kind {
+                Kind::Sort => continue,
+                Kind::Term => graph.terms.get_mut(idx as usize),
+                Kind::Theorem => graph.theorems.get_mut(idx as usize),
+            };
+
+            let deps = match deps {
+                Some(deps) => deps,
+                None => continue,
+            };
+
+            if kind == Kind::Theorem {
+                graph.offsets[idx as usize] = Some(statement.offset);
+
+                if statement.kind == Statement::Axiom || !statement.proof.is_empty() {
+                    graph.statements[idx as usize] = Some(statement.kind);
+                }
+            }
+
+            let proof =
+                statement
+                    .proof_commands()
+                    .filter_map(|(_, command)| match command.opcode {
+                        Proof::Term | Proof::TermSave => term(command.operand),
+                        Proof::Thm | Proof::ThmSave => theorem(command.operand),
+                        _ => None,
+                    });
+
+            deps.extend(proof);
+        }
+
+        for deps in graph.terms.iter_mut().chain(graph.theorems.iter_mut()) {
+            deps.sort_unstable();
+            deps.dedup();
+        }
+
+        graph
+    }
+
+    /// Returns an iterator over all nodes of the graph, terms first.
+    pub fn nodes(&self) -> impl Iterator<Item = Node> {
+        let terms = (0..self.terms.len() as u32).map(Node::Term);
+        let theorems = (0..self.theorems.len() as u32).map(Node::Theorem);
+
+        terms.chain(theorems)
+    }
+
+    /// Returns the direct dependencies of a node, sorted and without
+    /// duplicates, or an empty slice if the node is not part of the graph.
+    pub fn dependencies(&self, node: Node) -> &[Node] {
+        let deps = match node {
+            Node::Term(idx) => self.terms.get(idx as usize),
+            Node::Theorem(idx) => self.theorems.get(idx as usize),
+        };
+
+        deps.map_or(&[], |deps| &deps[..])
+    }
+
+    /// Returns all nodes the given node transitively depends on, sorted and
+    /// without duplicates. The node itself is only included if it is part of
+    /// a cycle.
+    pub fn transitive_dependencies(&self, node: Node) -> Vec<Node> {
+        let mut visited = Visited::new(self);
+        let mut stack: Vec<_> = self.dependencies(node).to_vec();
+
+        while let Some(node) = stack.pop() {
+            if visited.insert(node) {
+                stack.extend_from_slice(self.dependencies(node));
+            }
+        }
+
+        visited.into_nodes()
+    }
+
+    /// Returns whether the theorem with the given index is declared as an
+    /// axiom.
+    pub fn is_axiom(&self, theorem: u32) -> bool {
+        self.statements.get(theorem as usize) == Some(&Some(Statement::Axiom))
+    }
+
+    /// Returns the axioms and unproven theorems the theorem with the given
+    /// index transitively depends on, including the theorem itself.
+    pub fn axioms_used(&self, theorem: u32) -> AxiomFootprint {
+        let mut footprint = AxiomFootprint::default();
+
+        let node = Node::Theorem(theorem);
+        let mut deps = self.transitive_dependencies(node);
+
+        if theorem < self.theorems.len() as u32 && !deps.contains(&node) {
+            deps.push(node);
+            deps.sort_unstable();
+        }
+
+        for dep in deps {
+            if let Node::Theorem(idx) = dep {
+                match self.statements[idx as usize] {
+                    Some(Statement::Axiom) => footprint.axioms.push(idx),
+                    Some(_) => {}
+                    None => footprint.unproven.push(idx),
+                }
+            }
+        }
+
+        footprint
+    }
+
+    /// Returns the theorems that are not proven in the file, sorted by index.
+    ///
+    /// These are the theorems without a proof stream, and all theorems that
+    /// transitively depend on one. Axioms count as proven. A release build can
+    /// check that this is empty, and print the theorems with `Unproven::display`
+    /// otherwise.
+    pub fn unproven(&self) -> Vec<Unproven> {
+        let num = self.theorems.len();
+
+        let mut dependents = vec![Vec::new(); num];
+
+        for (idx, deps) in self.theorems.iter().enumerate() {
+            for &dep in deps {
+                if let Node::Theorem(dep) = dep {
+                    dependents[dep as usize].push(idx as u32);
+                }
+            }
+        }
+
+        let mut missing = vec![Vec::new(); num];
+
+        for (root, statement) in (0..).zip(&self.statements) {
+            if statement.is_some() {
+                continue;
+            }
+
+            let mut visited = Visited::new(self);
+            let mut stack = vec![root];
+
+            while let Some(idx) = stack.pop() {
+                if visited.insert(Node::Theorem(idx)) {
+                    missing[idx as usize].push(root);
+                    stack.extend_from_slice(&dependents[idx as usize]);
+                }
+            }
+        }
+
+        (0..)
+            .zip(missing)
+            .filter(|(_, missing)| !missing.is_empty())
+            .map(|(theorem, missing)| Unproven {
+                theorem,
+                offset: self.offsets[theorem as usize],
+                missing,
+            })
+            .collect()
+    }
+
+    /// Returns all nodes that no other node depends on, such as unused lemmas
+    /// and the final results of the file.
+    pub fn unused(&self) -> Vec<Node> {
+        let mut used = Visited::new(self);
+
+        for node in self.nodes() {
+            for &dep in self.dependencies(node) {
+                if dep != node {
+                    used.insert(dep);
+                }
+            }
+        }
+
+        self.nodes().filter(|&node| !used.contains(node)).collect()
+    }
+
+    /// Write the graph in the DOT format of Graphviz, labelling the nodes with
+    /// names from the name table if one is given. Nodes without a name, or
+    /// whose name is not valid UTF-8, are labelled with their node id.
+    pub fn write_dot<W: Write>(&self, w: &mut W, names: Option<&NameTable>) -> fmt::Result {
+        writeln!(w, "digraph dependencies {{")?;
+
+        for node in self.nodes() {
+            write!(w, "  {} [label=\"", NodeId(node))?;
+
+            match name(names, node) {
+                Some(name) => escape(w, name, false)?,
+                None => write!(w, "{}", NodeId(node))?,
+            }
+
+            let shape = match node {
+                Node::Term(_) => "box",
+                Node::Theorem(_) => "ellipse",
+            };
+
+            writeln!(w, "\", shape={}];", shape)?;
+        }
+
+        for node in self.nodes() {
+            for &dep in self.dependencies(node) {
+                writeln!(w, "  {} -> {};", NodeId(node), NodeId(dep))?;
+            }
+        }
+
+        writeln!(w, "}}")
+    }
+
+    /// Write the graph as an adjacency list in JSON, labelling the nodes with
+    /// names from the name table if one is given.
+    ///
+    /// The output is an object with a `terms` and a `theorems` array, indexed
+    /// by term and theorem respectively. Each element is an object with the
+    /// `name` of the node, or `null` if it has no name that is valid UTF-8, and
+    /// the indices of the `terms` and `theorems` it depends on.
+    pub fn write_json<W: Write>(&self, w: &mut W, names: Option<&NameTable>) -> fmt::Result {
+        write!(w, "{{\"terms\":")?;
+        write_json_section(w, &self.terms, Node::Term, names)?;
+        write!(w, ",\"theorems\":")?;
+        write_json_section(w, &self.theorems, Node::Theorem, names)?;
+        writeln!(w, "}}")
+    }
+}
+
+impl Unproven {
+    /// Returns a line describing the theorem, labelled with names from the
+    /// name table if one is given.
+    pub fn display<'b>(&'b self, names: Option<&'b NameTable<'b>>) -> UnprovenDisplay<'b> {
+        UnprovenDisplay {
+            unproven: self,
+            names,
+        }
+    }
+}
+
+/// A line describing an unproven theorem, such as `foo at offset 120: depends
+/// on bar, baz` or `bar at offset 80: no proof`.
+pub struct UnprovenDisplay<'a> {
+    unproven: &'a Unproven,
+    names: Option<&'a NameTable<'a>>,
+}
+
+impl<'a> UnprovenDisplay<'a> {
+    fn label(&self, f: &mut fmt::Formatter, theorem: u32) -> fmt::Result {
+        let node = Node::Theorem(theorem);
+
+        match name(self.names, node) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{}", NodeId(node)),
+        }
+    }
+}
+
+impl<'a> fmt::Display for UnprovenDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unproven = self.unproven;
+
+        self.label(f, unproven.theorem)?;
+
+        match unproven.offset {
+            Some(offset) => write!(f, " at offset {}", offset)?,
+            None => write!(f, " without statement")?,
+        }
+
+        if unproven.missing.contains(&unproven.theorem) {
+            return write!(f, ": no proof");
+        }
+
+        write!(f, ": depends on ")?;
+
+        for (i, &theorem) in unproven.missing.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            self.label(f, theorem)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A set of nodes of a graph.
+struct Visited {
+    terms: Vec<bool>,
+    theorems: Vec<bool>,
+}
+
+impl Visited {
+    fn new(graph: &DependencyGraph) -> Visited {
+        Visited {
+            terms: vec![false; graph.terms.len()],
+            theorems: vec![false; graph.theorems.len()],
+        }
+    }
+
+    fn slot(&mut self, node: Node) -> Option<&mut bool> {
+        match node {
+            Node::Term(idx) => self.terms.get_mut(idx as usize),
+            Node::Theorem(idx) => self.theorems.get_mut(idx as usize),
+        }
+    }
+
+    /// Insert a node, and return whether it was not in the set before.
+    fn insert(&mut self, node: Node) -> bool {
+        match self.slot(node) {
+            Some(slot) if !*slot => {
+                *slot = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn contains(&self, node: Node) -> bool {
+        let slot = match node {
+            Node::Term(idx) => self.terms.get(idx as usize),
+            Node::Theorem(idx) => self.theorems.get(idx as usize),
+        };
+
+        slot == Some(&true)
+    }
+
+    fn into_nodes(self) -> Vec<Node> {
+        let terms = (0..)
+            .zip(self.terms)
+            .filter(|x| x.1)
+            .map(|x| Node::Term(x.0));
+        let theorems = (0..)
+            .zip(self.theorems)
+            .filter(|x| x.1)
+            .map(|x| Node::Theorem(x.0));
+
+        terms.chain(theorems).collect()
+    }
+}
+
+struct NodeId(Node);
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Node::Term(idx) => write!(f, "term{}", idx),
+            Node::Theorem(idx) => write!(f, "thm{}", idx),
+        }
+    }
+}
+
+fn name<'a>(names: Option<&NameTable<'a>>, node: Node) -> Option<&'a str> {
+    let (kind, idx) = match node {
+        Node::Term(idx) => (Kind::Term, idx),
+        Node::Theorem(idx) => (Kind::Theorem, idx),
+    };
+
+    names?.section(kind).get(idx as u64)?.to_str().ok()
+}
+
+/// Write a string with quotes and backslashes escaped, and with control
+/// characters escaped as well if `json` is set.
+fn escape<W: Write>(w: &mut W, s: &str, json: bool) -> fmt::Result {
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => write!(w, "\\{}", c)?,
+            c if json && (c as u32) < 0x20 => write!(w, "\\u{:04x}", c as u32)?,
+            c => w.write_char(c)?,
+        }
+    }
+
+    Ok(())
+}
+
+fn write_json_section<W: Write>(
+    w: &mut W,
+    section: &[Vec<Node>],
+    node: fn(u32) -> Node,
+    names: Option<&NameTable>,
+) -> fmt::Result {
+    write!(w, "[")?;
+
+    for (idx, deps) in section.iter().enumerate() {
+        if idx > 0 {
+            write!(w, ",")?;
+        }
+
+        write!(w, "{{\"name\":")?;
+
+        match name(names, node(idx as u32)) {
+            Some(name) => {
+                write!(w, "\"")?;
+                escape(w, name, true)?;
+                write!(w, "\"")?;
+            }
+            None => write!(w, "null")?,
+        }
+
+        let terms = deps.iter().filter_map(|dep| match dep {
+            Node::Term(idx) => Some(*idx),
+            Node::Theorem(_) => None,
+        });
+
+        let theorems = deps.iter().filter_map(|dep| match dep {
+            Node::Theorem(idx) => Some(*idx),
+            Node::Term(_) => None,
+        });
+
+        write!(w, ",\"terms\":")?;
+        write_list(w, terms)?;
+        write!(w, ",\"theorems\":")?;
+        write_list(w, theorems)?;
+        write!(w, "}}")?;
+    }
+
+    write!(w, "]")
+}
+
+fn write_list<W: Write, I: Iterator<Item = u32>>(w: &mut W, items: I) -> fmt::Result {
+    write!(w, "[")?;
+
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(w, ",")?;
+        }
+
+        write!(w, "{}", item)?;
+    }
+
+    write!(w, "]")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use core::convert::TryInto;
+
+    use super::*;
+    use crate::testing;
+
+    const IM: u32 = 0;
+    const NOT: u32 = 1;
+    const NN: u32 = 2;
+    const AX: u32 = 0;
+    const A1: u32 = 1;
+    const A2: u32 = 2;
+
+    /// Add a definition `nn` using `not`, a theorem using `im` and `ax` whose
+    /// name needs escaping, and a theorem `a2` using `nn` and the first
+    /// theorem to `testing::logic`. The name of `nn` is not valid UTF-8.
+    fn file() -> Vec<u8> {
+        let mut builder = testing::logic();
+
+        builder.def(
+            "nn",
+            0,
+            &[0],
+            testing::unify(&[(Unify::Term, NOT), (Unify::Term, NOT), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Term, NOT), (Proof::Term, NOT)]),
+        );
+        builder.theorem(
+            "a\"1\\\t",
+            &[0],
+            testing::unify(&[(Unify::Term, IM), (Unify::Ref, 0), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Thm, AX)]),
+        );
+        builder.theorem(
+            "a2",
+            &[0],
+            testing::unify(&[(Unify::Term, NN), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Thm, A1)]),
+        );
+
+        let mut file = builder.build();
+        let entry = testing::name_entry(&file, 3);
+        let name = u64::from_le_bytes(file[entry + 8..entry + 16].try_into().unwrap());
+        file[name as usize] = 0xFF;
+
+        file
+    }
+
+    #[test]
+    fn edges() {
+        let file = file();
+        let graph = DependencyGraph::new(&Mmb::from(&file).unwrap());
+
+        assert_eq!(graph.dependencies(Node::Term(IM)), []);
+        assert_eq!(graph.dependencies(Node::Term(NN)), [Node::Term(NOT)]);
+        assert_eq!(graph.dependencies(Node::Theorem(AX)), []);
+        assert_eq!(
+            graph.dependencies(Node::Theorem(A1)),
+            [Node::Term(IM), Node::Theorem(AX)]
+        );
+        assert_eq!(
+            graph.dependencies(Node::Theorem(A2)),
+            [Node::Term(NN), Node::Theorem(A1)]
+        );
+        assert_eq!(graph.dependencies(Node::Theorem(3)), []);
+    }
+
+    #[test]
+    fn transitive_dependencies() {
+        let file = file();
+        let graph = DependencyGraph::new(&Mmb::from(&file).unwrap());
+
+        assert_eq!(
+            graph.transitive_dependencies(Node::Theorem(A2)),
+            [
+                Node::Term(IM),
+                Node::Term(NOT),
+                Node::Term(NN),
+                Node::Theorem(AX),
+                Node::Theorem(A1)
+            ]
+        );
+        assert_eq!(graph.transitive_dependencies(Node::Term(NOT)), []);
+    }
+
+    #[test]
+    fn unused() {
+        let file = file();
+        let graph = DependencyGraph::new(&Mmb::from(&file).unwrap());
+
+        assert_eq!(graph.unused(), [Node::Theorem(A2)]);
+    }
+
+    #[test]
+    fn dot_escapes_names() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+        let names = mmb.index().unwrap().name_table().unwrap();
+        let graph = DependencyGraph::new(&mmb);
+
+        let mut dot = String::new();
+        graph.write_dot(&mut dot, Some(&names)).unwrap();
+
+        assert_eq!(
+            dot.lines().collect::<Vec<_>>(),
+            [
+                "digraph dependencies {",
+                "  term0 [label=\"im\", shape=box];",
+                "  term1 [label=\"not\", shape=box];",
+                "  term2 [label=\"term2\", shape=box];",
+                "  thm0 [label=\"ax\", shape=ellipse];",
+                "  thm1 [label=\"a\\\"1\\\\\t\", shape=ellipse];",
+                "  thm2 [label=\"a2\", shape=ellipse];",
+                "  term2 -> term1;",
+                "  thm1 -> term0;",
+                "  thm1 -> thm0;",
+                "  thm2 -> term2;",
+                "  thm2 -> thm1;",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_escapes_names() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+        let names = mmb.index().unwrap().name_table().unwrap();
+        let graph = DependencyGraph::new(&mmb);
+
+        let mut json = String::new();
+        graph.write_json(&mut json, Some(&names)).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["terms"][1]["name"], "not");
+        assert_eq!(value["terms"][2]["name"], serde_json::Value::Null);
+        assert_eq!(value["terms"][2]["terms"], serde_json::json!([NOT]));
+        assert_eq!(value["theorems"][1]["name"], "a\"1\\\t");
+        assert_eq!(value["theorems"][2]["terms"], serde_json::json!([NN]));
+        assert_eq!(value["theorems"][2]["theorems"], serde_json::json!([A1]));
+        assert!(json.contains(r#""a\"1\\\u0009""#));
+    }
+
+    #[test]
+    fn axioms_reached_through_other_theorems() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+
+        // `a2` only invokes the first theorem, which invokes `ax`.
+        assert_eq!(
+            mmb.axioms_used(A2),
+            AxiomFootprint {
+                axioms: vec![AX],
+                unproven: Vec::new(),
+            }
+        );
+        assert_eq!(mmb.axioms_used(AX).axioms, [AX]);
+    }
+
+    #[test]
+    fn theorems_without_proof_are_unproven() {
+        let mut builder = testing::logic();
+        let unify = testing::unify(&[(Unify::Ref, 0)]);
+        let lemma = builder.theorem("lemma", &[0], unify.clone(), Vec::new());
+        let main = builder.theorem(
+            "main",
+            &[0],
+            unify,
+            testing::proof(&[(Proof::Ref, 0), (Proof::Thm, lemma), (Proof::Thm, AX)]),
+        );
+
+        let file = builder.build();
+        let mmb = Mmb::from(&file).unwrap();
+
+        assert_eq!(
+            mmb.axioms_used(main),
+            AxiomFootprint {
+                axioms: vec![AX],
+                unproven: vec![lemma],
+            }
+        );
+        assert_eq!(mmb.axioms_used(lemma).unproven, [lemma]);
+    }
+
+    #[test]
+    fn unproven_theorems_and_their_dependents() {
+        let mut builder = testing::logic();
+        let unify = || testing::unify(&[(Unify::Ref, 0)]);
+        let lemma = builder.theorem("lemma", &[0], unify(), Vec::new());
+        let main = builder.theorem(
+            "main",
+            &[0],
+            unify(),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Thm, lemma)]),
+        );
+        let other = builder.theorem(
+            "other",
+            &[0],
+            unify(),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Thm, main)]),
+        );
+        builder.theorem(
+            "fine",
+            &[0],
+            unify(),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Thm, AX)]),
+        );
+        let late = builder.theorem("late", &[0], unify(), Vec::new());
+
+        // End the statement stream before the last theorem.
+        let mut file = builder.build();
+        let proofs_ptr = Mmb::from(&file).unwrap().proofs_ptr();
+        file[proofs_ptr + 51] = 0;
+
+        let mmb = Mmb::from(&file).unwrap();
+        let names = mmb.index().unwrap().name_table().unwrap();
+        let unproven = mmb.unproven();
+
+        assert_eq!(
+            unproven,
+            [
+                Unproven {
+                    theorem: lemma,
+                    offset: Some(20),
+                    missing: vec![lemma],
+                },
+                Unproven {
+                    theorem: main,
+                    offset: Some(25),
+                    missing: vec![lemma],
+                },
+                Unproven {
+                    theorem: other,
+                    offset: Some(34),
+                    missing: vec![lemma],
+                },
+                Unproven {
+                    theorem: late,
+                    offset: None,
+                    missing: vec![late],
+                },
+            ]
+        );
+
+        let lines: Vec<_> = unproven
+            .iter()
+            .map(|unproven| unproven.display(Some(&names)).to_string())
+            .collect();
+
+        assert_eq!(
+            lines,
+            [
+                "lemma at offset 20: no proof",
+                "main at offset 25: depends on lemma",
+                "other at offset 34: depends on lemma",
+                "late without statement: no proof",
+            ]
+        );
+        assert_eq!(
+            unproven[1].display(None).to_string(),
+            "thm2 at offset 25: depends on thm1"
+        );
+    }
+}
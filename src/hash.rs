@@ -0,0 +1,994 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::command::{ProofCommandIter, UnifyCommandIter};
+use crate::index::Kind;
+use crate::opcode::{Proof, Unify};
+use crate::Mmb;
+
+/// The content hashes of a term or theorem.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemHash {
+    /// The hash of the signature of the item, that is its binders, its return
+    /// type or sort for terms, and the expressions of its unify stream.
+    pub statement: u64,
+    /// The hash of the proof stream of the item, which includes the body of a
+    /// definition, or `None` if the item has no proof stream.
+    pub proof: Option<u64>,
+}
+
+/// Stable content hashes of all terms and theorems of a proof file.
+///
+/// The hashes are computed over decoded data, so they do not depend on the
+/// width used to encode an operand, and they are the same on every platform
+/// and across versions of this crate with the same major version.
+///
+/// References to terms and theorems are hashed by the statement hash of the
+/// referenced item rather than by its index, so that inserting or removing an
+/// item does not change the hashes of unrelated items. Statement hashes never
+/// depend on proofs, so proofs may reference any item this way, while the
+/// unify stream of a definition may only reference terms declared before it.
+/// Other references are hashed by index, as are all references to sorts.
+///
+/// The expressions of unify streams are hashed as trees, with references to
+/// saved subterms replaced by the subterms themselves, so that saving and
+/// repeating a subterm hash equally. Proof streams are hashed the same way, as
+/// trees of expressions, proofs and conversion proofs, where references to the
+/// heap are replaced by the hashes of the entries they refer to. Proofs that
+/// differ only in which subterms, subproofs and conversions are saved and
+/// referenced hash equally.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hashes {
+    terms: Vec<ItemHash>,
+    theorems: Vec<ItemHash>,
+}
+
+impl Hashes {
+    /// Compute the hashes of all terms and theorems of a proof file.
+    ///
+    /// Commands past the first command that cannot be parsed are ignored.
+    pub fn new(mmb: &Mmb) -> Hashes {
+        let mut hashes = Hashes {
+            terms: Vec::with_capacity(mmb.num_terms() as usize),
+            theorems: Vec::with_capacity(mmb.num_theorems() as usize),
+        };
+
+        for idx in 0..mmb.num_terms() {
+            let statement = match mmb.term(idx) {
+                Some(term) => {
+                    let mut h = Fnv::new();
+                    h.write_u64(term.sort as u64);
+                    h.write_u64(term.ret_ty);
+                    binders(&mut h, term.binders());
+                    unify(&mut h, mmb, &hashes.terms, term.num_args(), term.unify);
+                    h.finish()
+                }
+                None => missing(Kind::Term, idx),
+            };
+
+            hashes.terms.push(ItemHash {
+                statement,
+                proof: None,
+            });
+        }
+
+        for idx in 0..mmb.num_theorems() {
+            let statement = match mmb.theorem(idx) {
+                Some(theorem) => {
+                    let mut h = Fnv::new();
+                    binders(&mut h, theorem.binders());
+                    unify(
+                        &mut h,
+                        mmb,
+                        &hashes.terms,
+                        theorem.num_args(),
+                        theorem.unify,
+                    );
+                    h.finish()
+                }
+                None => missing(Kind::Theorem, idx),
+            };
+
+            hashes.theorems.push(ItemHash {
+                statement,
+                proof: None,
+            });
+        }
+
+        let mut proofs = [
+            vec![],
+            vec![None; hashes.terms.len()],
+            vec![None; hashes.theorems.len()],
+        ];
+
+        let arities = Arities::new(mmb);
+
+        for (kind, idx, statement) in mmb.declarations() {
+            let slot = match proofs[kind as usize].get_mut(idx as usize) {
+                Some(slot) => slot,
+                None => continue,
+            };
+
+            if statement.proof.is_empty() {
+                continue;
+            }
+
+            let num_args = match kind {
+                Kind::Sort => 0,
+                Kind::Term => arities.terms[idx as usize],
+                Kind::Theorem => arities.theorems[idx as usize].0,
+            };
+
+            let mut h = Fnv::new();
+            h.write_u64(statement.kind as u64);
+            proof(
+                &mut h,
+                &hashes,
+                &arities,
+                num_args,
+                statement.proof_commands(),
+            );
+
+            *slot = Some(h.finish());
+        }
+
+        for (item, proof) in hashes.terms.iter_mut().zip(&proofs[Kind::Term as usize]) {
+            item.proof = *proof;
+        }
+
+        for (item, proof) in hashes
+            .theorems
+            .iter_mut()
+            .zip(&proofs[Kind::Theorem as usize])
+        {
+            item.proof = *proof;
+        }
+
+        hashes
+    }
+
+    /// Returns the hashes of the term with the given index
+    pub fn term(&self, idx: u32) -> Option<ItemHash> {
+        self.terms.get(idx as usize).copied()
+    }
+
+    /// Returns the hashes of the theorem with the given index
+    pub fn theorem(&self, idx: u32) -> Option<ItemHash> {
+        self.theorems.get(idx as usize).copied()
+    }
+
+    /// Returns the hashes of all terms, indexed by term
+    pub fn terms(&self) -> &[ItemHash] {
+        &self.terms
+    }
+
+    /// Returns the hashes of all theorems, indexed by theorem
+    pub fn theorems(&self) -> &[ItemHash] {
+        &self.theorems
+    }
+
+    /// Hash a reference to a term or theorem.
+    fn reference(&self, kind: Kind, idx: u32) -> u64 {
+        let item = match kind {
+            Kind::Sort => None,
+            Kind::Term => self.terms.get(idx as usize),
+            Kind::Theorem => self.theorems.get(idx as usize),
+        };
+
+        item.map_or_else(|| missing(kind, idx), |item| item.statement)
+    }
+}
+
+/// The hash of a reference to an item that does not exist.
+fn missing(kind: Kind, idx: u32) -> u64 {
+    let mut h = Fnv::new();
+    h.write_u64(u64::MAX);
+    h.write_u64(kind as u64);
+    h.write_u64(idx as u64);
+    h.finish()
+}
+
+fn binders<I: Iterator<Item = u64>>(h: &mut Fnv, binders: I) {
+    for binder in binders {
+        h.write_u64(binder);
+    }
+}
+
+/// The kind of a node of an expression or proof tree.
+#[derive(Copy, Clone)]
+#[repr(u8)]
+enum Node {
+    Var,
+    Dummy,
+    App,
+    Hyp,
+    Invalid,
+    Thm,
+    Conv,
+    Refl,
+    Symm,
+    Cong,
+    Unfold,
+}
+
+/// Hash a leaf of an expression or proof tree.
+fn leaf(node: Node, a: u64, b: u64) -> u64 {
+    let mut h = Fnv::new();
+    h.write_u64(node as u64);
+    h.write_u64(a);
+    h.write_u64(b);
+    h.finish()
+}
+
+/// A term application whose arguments are still being read.
+struct Frame {
+    hasher: Fnv,
+    remaining: usize,
+    /// The heap slot of the expression, if it is saved.
+    save: Option<usize>,
+}
+
+/// Hash the expressions of a unify stream as trees, with the heap references
+/// replaced by the hashes of the expressions they refer to.
+fn unify(h: &mut Fnv, mmb: &Mmb, terms: &[ItemHash], num_args: usize, stream: &[u8]) {
+    let mut heap: Vec<_> = (0..num_args as u64)
+        .map(|i| leaf(Node::Var, i, 0))
+        .collect();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut dummies = 0;
+
+    for (_, command) in UnifyCommandIter::new(stream, 0) {
+        let operand = command.operand;
+
+        let mut done = match command.opcode {
+            Unify::End => break,
+            Unify::Hyp => {
+                h.write_u64(Node::Hyp as u64);
+                continue;
+            }
+            Unify::Ref => match heap.get(operand as usize) {
+                Some(&hash) => hash,
+                None => leaf(Node::Invalid, operand as u64, 0),
+            },
+            Unify::Dummy => {
+                let hash = leaf(Node::Dummy, operand as u64, dummies);
+                dummies += 1;
+                heap.push(hash);
+                hash
+            }
+            Unify::Term | Unify::TermSave => {
+                let reference = match terms.get(operand as usize) {
+                    Some(term) => term.statement,
+                    None => missing(Kind::Term, operand),
+                };
+
+                let mut hasher = Fnv::new();
+                hasher.write_u64(Node::App as u64);
+                hasher.write_u64(reference);
+
+                // The heap slot of a saved expression is taken when the command
+                // is read, before the slots taken by its subexpressions, and
+                // filled in when the expression is complete.
+                let save = if command.opcode == Unify::TermSave {
+                    heap.push(leaf(Node::Invalid, operand as u64, 0));
+                    Some(heap.len() - 1)
+                } else {
+                    None
+                };
+
+                stack.push(Frame {
+                    hasher,
+                    remaining: mmb.term(operand).map_or(0, |term| term.num_args()),
+                    save,
+                });
+
+                match finish(&mut stack, &mut heap) {
+                    Some(hash) => hash,
+                    None => continue,
+                }
+            }
+        };
+
+        // Feed the completed expression into its parent, completing the parent
+        // as well if this was its last argument.
+        loop {
+            match stack.last_mut() {
+                Some(frame) => {
+                    frame.hasher.write_u64(done);
+                    frame.remaining -= 1;
+                }
+                None => {
+                    h.write_u64(done);
+                    break;
+                }
+            }
+
+            match finish(&mut stack, &mut heap) {
+                Some(hash) => done = hash,
+                None => break,
+            }
+        }
+    }
+
+    if !stack.is_empty() {
+        h.write_u64(Node::Invalid as u64);
+    }
+}
+
+/// Pop the top frame if all its arguments have been read, and return the hash
+/// of the completed expression.
+fn finish(stack: &mut Vec<Frame>, heap: &mut [u64]) -> Option<u64> {
+    if stack.last()?.remaining > 0 {
+        return None;
+    }
+
+    let frame = stack.pop()?;
+    let hash = frame.hasher.finish();
+
+    if let Some(slot) = frame.save.and_then(|slot| heap.get_mut(slot)) {
+        *slot = hash;
+    }
+
+    Some(hash)
+}
+
+/// The number of arguments of each term, and the number of arguments and
+/// hypotheses of each theorem.
+struct Arities {
+    terms: Vec<usize>,
+    theorems: Vec<(usize, usize)>,
+}
+
+impl Arities {
+    fn new(mmb: &Mmb) -> Arities {
+        let terms = (0..mmb.num_terms())
+            .map(|idx| mmb.term(idx).map_or(0, |term| term.num_args()))
+            .collect();
+
+        let theorems = (0..mmb.num_theorems())
+            .map(|idx| match mmb.theorem(idx) {
+                Some(theorem) => {
+                    let hyps = theorem
+                        .unify_commands()
+                        .filter(|(_, command)| command.opcode == Unify::Hyp)
+                        .count();
+
+                    (theorem.num_args(), hyps)
+                }
+                None => (0, 0),
+            })
+            .collect();
+
+        Arities { terms, theorems }
+    }
+}
+
+/// An entry of the stack or the heap of a proof stream.
+#[derive(Copy, Clone)]
+enum Value {
+    /// An expression or a proof, with the expression it proves.
+    Done { hash: u64, expr: Option<usize> },
+    /// A proof by conversion, or a saved conversion, whose hash is known once
+    /// the conversion proof of the node is complete.
+    Pending { node: usize, expr: Option<usize> },
+    /// A convertibility obligation, whose proof is an argument of the node.
+    Obligation {
+        node: usize,
+        lhs: Option<usize>,
+        rhs: Option<usize>,
+    },
+}
+
+/// A conversion proof with obligations that are not discharged yet.
+struct ConvNode {
+    /// The hasher of the node, or `None` if the hash of the node is the hash
+    /// of its only argument.
+    hasher: Option<Fnv>,
+    hash: Option<u64>,
+    remaining: usize,
+    parent: Option<usize>,
+}
+
+/// The expressions and conversion proofs of a proof stream.
+struct ProofTrees {
+    /// The arguments of each expression, which are needed to split
+    /// convertibility obligations by congruence.
+    exprs: Vec<Vec<Option<usize>>>,
+    nodes: Vec<ConvNode>,
+}
+
+impl ProofTrees {
+    fn expr(&mut self, hash: u64, args: Vec<Option<usize>>) -> Value {
+        self.exprs.push(args);
+
+        Value::Done {
+            hash,
+            expr: Some(self.exprs.len() - 1),
+        }
+    }
+
+    fn hash(&self, value: Value) -> u64 {
+        match value {
+            Value::Done { hash, .. } => hash,
+            Value::Pending { node, .. } => self
+                .nodes
+                .get(node)
+                .and_then(|node| node.hash)
+                .unwrap_or_else(|| leaf(Node::Invalid, 0, 0)),
+            Value::Obligation { .. } => leaf(Node::Invalid, 0, 0),
+        }
+    }
+
+    fn node(&mut self, hasher: Option<Fnv>, remaining: usize, parent: Option<usize>) -> usize {
+        self.nodes.push(ConvNode {
+            hasher,
+            hash: None,
+            remaining,
+            parent,
+        });
+
+        self.nodes.len() - 1
+    }
+
+    /// Add the proof of an obligation to its node, completing the node and
+    /// its parents if this was their last obligation.
+    fn discharge(&mut self, mut node: usize, mut hash: u64) {
+        while let Some(current) = self.nodes.get_mut(node) {
+            if current.remaining == 0 {
+                return;
+            }
+
+            match &mut current.hasher {
+                Some(hasher) => hasher.write_u64(hash),
+                None => current.hash = Some(hash),
+            }
+
+            current.remaining -= 1;
+
+            if current.remaining > 0 {
+                return;
+            }
+
+            hash = match &current.hasher {
+                Some(hasher) => hasher.finish(),
+                None => current.hash.unwrap_or(hash),
+            };
+            current.hash = Some(hash);
+
+            match current.parent {
+                Some(parent) => node = parent,
+                None => return,
+            }
+        }
+    }
+}
+
+/// Hash a proof stream as trees of expressions, proofs and conversion proofs,
+/// with the heap references replaced by the hashes of the entries they refer
+/// to.
+fn proof(
+    h: &mut Fnv,
+    hashes: &Hashes,
+    arities: &Arities,
+    num_args: usize,
+    commands: ProofCommandIter,
+) {
+    let mut trees = ProofTrees {
+        exprs: Vec::new(),
+        nodes: Vec::new(),
+    };
+
+    let mut heap: Vec<Value> = (0..num_args as u64)
+        .map(|i| trees.expr(leaf(Node::Var, i, 0), Vec::new()))
+        .collect();
+    let mut stack: Vec<Value> = Vec::new();
+    let mut dummies = 0;
+    let mut hyps = 0;
+
+    let invalid = |operand: u32| Value::Done {
+        hash: leaf(Node::Invalid, operand as u64, 0),
+        expr: None,
+    };
+
+    let expr = |value: Value| match value {
+        Value::Done { expr, .. } | Value::Pending { expr, .. } => expr,
+        Value::Obligation { .. } => None,
+    };
+
+    for (_, command) in commands {
+        let operand = command.operand;
+
+        match command.opcode {
+            Proof::End => break,
+            Proof::Term | Proof::TermSave => {
+                let num_args = arities.terms.get(operand as usize).copied();
+                let args = pop(&mut stack, num_args.unwrap_or(0));
+
+                let mut hasher = Fnv::new();
+                hasher.write_u64(Node::App as u64);
+                hasher.write_u64(hashes.reference(Kind::Term, operand));
+
+                for &arg in &args {
+                    hasher.write_u64(trees.hash(arg));
+                }
+
+                let value = trees.expr(hasher.finish(), args.into_iter().map(expr).collect());
+                stack.push(value);
+
+                if command.opcode == Proof::TermSave {
+                    heap.push(value);
+                }
+            }
+            Proof::Ref => {
+                let value = heap.get(operand as usize).copied();
+                stack.push(value.unwrap_or_else(|| invalid(operand)));
+            }
+            Proof::Dummy => {
+                let value = trees.expr(leaf(Node::Dummy, operand as u64, dummies), Vec::new());
+                dummies += 1;
+                stack.push(value);
+                heap.push(value);
+            }
+            Proof::Thm | Proof::ThmSave => {
+                let target = stack.pop().unwrap_or_else(|| invalid(0));
+                let (num_args, num_hyps) = arities
+                    .theorems
+                    .get(operand as usize)
+                    .copied()
+                    .unwrap_or((0, 0));
+
+                let mut hasher = Fnv::new();
+                hasher.write_u64(Node::Thm as u64);
+                hasher.write_u64(hashes.reference(Kind::Theorem, operand));
+                hasher.write_u64(trees.hash(target));
+
+                for arg in pop(&mut stack, num_args + num_hyps) {
+                    hasher.write_u64(trees.hash(arg));
+                }
+
+                let value = Value::Done {
+                    hash: hasher.finish(),
+                    expr: expr(target),
+                };
+                stack.push(value);
+
+                if command.opcode == Proof::ThmSave {
+                    heap.push(value);
+                }
+            }
+            Proof::Hyp => {
+                let target = stack.pop().unwrap_or_else(|| invalid(0));
+
+                heap.push(Value::Done {
+                    hash: leaf(Node::Hyp, hyps, trees.hash(target)),
+                    expr: expr(target),
+                });
+                hyps += 1;
+            }
+            Proof::Conv => {
+                let proof = stack.pop().unwrap_or_else(|| invalid(0));
+                let target = stack.pop().unwrap_or_else(|| invalid(0));
+
+                let mut hasher = Fnv::new();
+                hasher.write_u64(Node::Conv as u64);
+                hasher.write_u64(trees.hash(target));
+                hasher.write_u64(trees.hash(proof));
+
+                let node = trees.node(Some(hasher), 1, None);
+
+                stack.push(Value::Pending {
+                    node,
+                    expr: expr(target),
+                });
+                stack.push(Value::Obligation {
+                    node,
+                    lhs: expr(target),
+                    rhs: expr(proof),
+                });
+            }
+            Proof::Refl => {
+                if let Some(Value::Obligation { node, .. }) = stack.pop() {
+                    trees.discharge(node, leaf(Node::Refl, 0, 0));
+                }
+            }
+            Proof::Symm => {
+                if let Some(Value::Obligation { node, lhs, rhs }) = stack.pop() {
+                    let mut hasher = Fnv::new();
+                    hasher.write_u64(Node::Symm as u64);
+
+                    let node = trees.node(Some(hasher), 1, Some(node));
+
+                    stack.push(Value::Obligation {
+                        node,
+                        lhs: rhs,
+                        rhs: lhs,
+                    });
+                }
+            }
+            Proof::Cong => {
+                if let Some(Value::Obligation { node, lhs, rhs }) = stack.pop() {
+                    let args = |expr: Option<usize>| {
+                        expr.and_then(|expr| trees.exprs.get(expr))
+                            .cloned()
+                            .unwrap_or_default()
+                    };
+
+                    let (lhs, rhs) = (args(lhs), args(rhs));
+                    let num_args = core::cmp::max(lhs.len(), rhs.len());
+
+                    let mut hasher = Fnv::new();
+                    hasher.write_u64(Node::Cong as u64);
+
+                    if num_args == 0 {
+                        trees.discharge(node, hasher.finish());
+                        continue;
+                    }
+
+                    let node = trees.node(Some(hasher), num_args, Some(node));
+
+                    // The obligation of the first argument is on top.
+                    for i in (0..num_args).rev() {
+                        stack.push(Value::Obligation {
+                            node,
+                            lhs: lhs.get(i).copied().flatten(),
+                            rhs: rhs.get(i).copied().flatten(),
+                        });
+                    }
+                }
+            }
+            Proof::Unfold => {
+                let unfolded = stack.pop().unwrap_or_else(|| invalid(0));
+
+                if let Some(Value::Obligation { node, rhs, .. }) = stack.pop() {
+                    let mut hasher = Fnv::new();
+                    hasher.write_u64(Node::Unfold as u64);
+                    hasher.write_u64(trees.hash(unfolded));
+
+                    let node = trees.node(Some(hasher), 1, Some(node));
+
+                    stack.push(Value::Obligation {
+                        node,
+                        lhs: expr(unfolded),
+                        rhs,
+                    });
+                }
+            }
+            Proof::ConvCut => {
+                if let Some(Value::Obligation { node, lhs, rhs }) = stack.pop() {
+                    // The saved conversion proves the obligation, so it hashes
+                    // like a conversion proof written in place.
+                    let node = trees.node(None, 1, Some(node));
+
+                    stack.push(Value::Pending { node, expr: None });
+                    stack.push(Value::Obligation { node, lhs, rhs });
+                }
+            }
+            Proof::ConvRef => {
+                if let Some(Value::Obligation { node, .. }) = stack.pop() {
+                    let saved = heap.get(operand as usize).copied();
+                    let hash = trees.hash(saved.unwrap_or_else(|| invalid(operand)));
+
+                    trees.discharge(node, hash);
+                }
+            }
+            Proof::ConvSave => {
+                let conversion = stack.pop().unwrap_or_else(|| invalid(0));
+
+                heap.push(Value::Done {
+                    hash: trees.hash(conversion),
+                    expr: None,
+                });
+            }
+            Proof::Save => {
+                if let Some(&value) = stack.last() {
+                    heap.push(Value::Done {
+                        hash: trees.hash(value),
+                        expr: expr(value),
+                    });
+                }
+            }
+        }
+    }
+
+    for value in stack {
+        h.write_u64(trees.hash(value));
+    }
+}
+
+/// Pop `n` entries from the stack, and return them in the order they were
+/// pushed.
+fn pop(stack: &mut Vec<Value>, n: usize) -> Vec<Value> {
+    let n = core::cmp::min(n, stack.len());
+
+    stack.split_off(stack.len() - n)
+}
+
+/// The 64-bit FNV-1a hash function.
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub(crate) fn write_u64(&mut self, value: u64) {
+        for byte in value.to_le_bytes().iter() {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Add a theorem with one argument per unify stream to `testing::logic`,
+    /// and hash them.
+    fn statement_hashes(streams: &[&[(Unify, u32)]]) -> Vec<u64> {
+        let mut builder = testing::logic();
+
+        for stream in streams {
+            builder.axiom("ax", &[0], testing::unify(stream), Vec::new());
+        }
+
+        let file = builder.build();
+        let mmb = Mmb::from(&file).unwrap();
+
+        let hashes = Hashes::new(&mmb);
+        hashes.theorems()[1..]
+            .iter()
+            .map(|item| item.statement)
+            .collect()
+    }
+
+    /// Like `statement_hashes`, but for the proofs of theorems with one
+    /// argument and one hypothesis, where `ax` is a theorem with one argument
+    /// and no hypotheses.
+    fn proof_hashes(proofs: &[&[(Proof, u32)]]) -> Vec<Option<u64>> {
+        let mut builder = testing::logic();
+
+        let unify = testing::unify(&[(Unify::Ref, 0), (Unify::Hyp, 0), (Unify::Ref, 0)]);
+
+        for proof in proofs {
+            builder.theorem("thm", &[0], unify.clone(), testing::proof(proof));
+        }
+
+        let file = builder.build();
+        let mmb = Mmb::from(&file).unwrap();
+
+        let hashes = Hashes::new(&mmb);
+        hashes.theorems()[1..]
+            .iter()
+            .map(|item| item.proof)
+            .collect()
+    }
+
+    #[test]
+    fn saved_subterms_of_unify_streams_hash_like_inline_subterms() {
+        // im (not x) (not x)
+        let hashes = statement_hashes(&[
+            &[
+                (Unify::Term, 0),
+                (Unify::Term, 1),
+                (Unify::Ref, 0),
+                (Unify::Term, 1),
+                (Unify::Ref, 0),
+            ],
+            &[
+                (Unify::TermSave, 0),
+                (Unify::TermSave, 1),
+                (Unify::Ref, 0),
+                (Unify::Ref, 2),
+            ],
+            &[
+                (Unify::Term, 0),
+                (Unify::TermSave, 1),
+                (Unify::Ref, 0),
+                (Unify::Ref, 1),
+            ],
+        ]);
+
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(hashes[0], hashes[2]);
+    }
+
+    #[test]
+    fn different_unify_streams_hash_differently() {
+        let hashes = statement_hashes(&[
+            &[
+                (Unify::Term, 0),
+                (Unify::Term, 1),
+                (Unify::Ref, 0),
+                (Unify::Ref, 0),
+            ],
+            &[
+                (Unify::Term, 0),
+                (Unify::Ref, 0),
+                (Unify::Term, 1),
+                (Unify::Ref, 0),
+            ],
+        ]);
+
+        assert_ne!(hashes[0], hashes[1]);
+    }
+
+    #[test]
+    fn saved_subterms_of_proofs_hash_like_inline_subterms() {
+        let hashes = proof_hashes(&[
+            &[
+                (Proof::Ref, 0),
+                (Proof::Term, 1),
+                (Proof::Ref, 0),
+                (Proof::Term, 1),
+                (Proof::Term, 0),
+            ],
+            &[
+                (Proof::Ref, 0),
+                (Proof::TermSave, 1),
+                (Proof::Ref, 1),
+                (Proof::Term, 0),
+            ],
+            &[
+                (Proof::Ref, 0),
+                (Proof::Term, 1),
+                (Proof::Save, 0),
+                (Proof::Ref, 1),
+                (Proof::Term, 0),
+            ],
+            &[
+                (Proof::Ref, 0),
+                (Proof::Term, 1),
+                (Proof::Ref, 0),
+                (Proof::Term, 0),
+            ],
+        ]);
+
+        assert_eq!(hashes[0], hashes[1]);
+        assert_eq!(hashes[0], hashes[2]);
+        assert_ne!(hashes[0], hashes[3]);
+    }
+
+    #[test]
+    fn saved_subproofs_hash_like_inline_subproofs() {
+        // Both references to the hypothesis are shifted by the saved subproof.
+        let hashes = proof_hashes(&[
+            &[
+                (Proof::Ref, 0),
+                (Proof::Hyp, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 0),
+                (Proof::Thm, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 0),
+                (Proof::Thm, 0),
+                (Proof::Ref, 1),
+            ],
+            &[
+                (Proof::Ref, 0),
+                (Proof::Hyp, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 0),
+                (Proof::ThmSave, 0),
+                (Proof::Ref, 2),
+                (Proof::Ref, 1),
+            ],
+        ]);
+
+        assert_eq!(hashes[0], hashes[1]);
+    }
+
+    #[test]
+    fn saved_conversions_hash_like_inline_conversions() {
+        let hashes = proof_hashes(&[
+            &[
+                (Proof::Ref, 0),
+                (Proof::Hyp, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 1),
+                (Proof::Conv, 0),
+                (Proof::Refl, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 1),
+                (Proof::Conv, 0),
+                (Proof::Refl, 0),
+            ],
+            &[
+                (Proof::Ref, 0),
+                (Proof::Hyp, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 1),
+                (Proof::Conv, 0),
+                (Proof::ConvCut, 0),
+                (Proof::Refl, 0),
+                (Proof::ConvSave, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 1),
+                (Proof::Conv, 0),
+                (Proof::ConvRef, 2),
+            ],
+            &[
+                (Proof::Ref, 0),
+                (Proof::Hyp, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 1),
+                (Proof::Conv, 0),
+                (Proof::Symm, 0),
+                (Proof::Refl, 0),
+                (Proof::Ref, 0),
+                (Proof::Ref, 1),
+                (Proof::Conv, 0),
+                (Proof::Refl, 0),
+            ],
+        ]);
+
+        assert_eq!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+    }
+
+    /// Add a definition `nn`, a theorem `t1` using `ax` and a theorem `t2`
+    /// using `nn` and `t1` to `testing::logic`, with an unrelated term and
+    /// axiom declared either first or last. Returns the file and the indices
+    /// of `nn`, `t1` and `t2`.
+    fn reordered(unrelated_first: bool) -> (Vec<u8>, u32, u32, u32) {
+        let mut builder = testing::logic();
+        let (im, not, ax) = (0, 1, 0);
+
+        let unrelated = |builder: &mut testing::Builder| {
+            builder.term("x", 0, &[]);
+            builder.axiom("u", &[0], testing::unify(&[(Unify::Ref, 0)]), Vec::new());
+        };
+
+        if unrelated_first {
+            unrelated(&mut builder);
+        }
+
+        let nn = builder.def(
+            "nn",
+            0,
+            &[0],
+            testing::unify(&[(Unify::Term, not), (Unify::Term, not), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Term, not), (Proof::Term, not)]),
+        );
+        let t1 = builder.theorem(
+            "t1",
+            &[0],
+            testing::unify(&[(Unify::Term, im), (Unify::Ref, 0), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Thm, ax)]),
+        );
+        let t2 = builder.theorem(
+            "t2",
+            &[0],
+            testing::unify(&[(Unify::Term, nn), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Term, nn), (Proof::Thm, t1)]),
+        );
+
+        if !unrelated_first {
+            unrelated(&mut builder);
+        }
+
+        (builder.build(), nn, t1, t2)
+    }
+
+    #[test]
+    fn reordering_unrelated_items_keeps_hashes() {
+        let (first, nn, t1, t2) = reordered(true);
+        let (last, nn_last, t1_last, t2_last) = reordered(false);
+
+        assert_ne!((nn, t1, t2), (nn_last, t1_last, t2_last));
+
+        let first = Hashes::new(&Mmb::from(&first).unwrap());
+        let last = Hashes::new(&Mmb::from(&last).unwrap());
+
+        assert_eq!(first.term(nn), last.term(nn_last));
+        assert_eq!(first.theorem(t1), last.theorem(t1_last));
+        assert_eq!(first.theorem(t2), last.theorem(t2_last));
+        assert!(first.term(nn).unwrap().proof.is_some());
+        assert!(first.theorem(t2).unwrap().proof.is_some());
+    }
+}
@@ -0,0 +1,27 @@
+/// The size of the fixed part of the file header in bytes.
+///
+/// The header is followed by the sort table, which contains one byte per sort.
+pub const HEADER_SIZE: usize = 40;
+
+/// The fixed part of the file header.
+///
+/// All pointers are offsets relative to the start of the file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Header {
+    /// The version of the proof file format.
+    pub version: u8,
+    /// The number of sorts in the sort table.
+    pub num_sorts: u8,
+    /// The number of terms in the term table.
+    pub num_terms: u32,
+    /// The number of theorems in the theorem table.
+    pub num_theorems: u32,
+    /// The pointer to the term table.
+    pub terms_ptr: u32,
+    /// The pointer to the theorem table.
+    pub theorems_ptr: u32,
+    /// The pointer to the proof section.
+    pub proofs_ptr: u32,
+    /// The pointer to the index, or zero if the file has no index.
+    pub index_ptr: u64,
+}
@@ -5,9 +5,13 @@
 /// The index is a collection of tables that in turn contain domain specific
 /// data. Because the index is designed to be extensible, each table entry is
 /// identified by an id that determines how the data should be interpreted.
+///
+/// Besides the name table of the MM0 format, this crate defines the source span
+/// table, the proof source map and the sorted name order. These tables are not
+/// part of the MM0 format, so other tools ignore them.
 #[derive(Debug)]
 pub struct Index<'a> {
-    pub(crate) file: &'a [u8],
+    pub(crate) file: parser::Window<'a>,
 
     pub(crate) num_sorts: u8,
     pub(crate) num_terms: u32,
@@ -20,13 +24,48 @@ pub struct Index<'a> {
     pub(crate) entries: &'a [u8],
 }
 
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 use crate::parser;
+#[cfg(feature = "alloc")]
+use crate::Mmb;
 
-pub use self::name_table::NameTable;
+pub use self::name_table::{Kind, NameTable};
+pub use self::proof_map::ProofSourceMap;
+pub use self::source_spans::SourceSpanTable;
 
 pub mod name_table;
+pub mod proof_map;
+#[cfg(feature = "alloc")]
+mod search;
+pub mod source_spans;
 
 impl<'a> Index<'a> {
+    /// Returns the number of table entries in the index.
+    pub fn num_entries(&self) -> u64 {
+        self.num_entries
+    }
+
+    /// Returns the name table of the index, or `None` if the index does not
+    /// contain a name table.
+    pub fn name_table(&self) -> Option<NameTable<'a>> {
+        self.iter().find_map(|entry| entry.as_name_table(self))
+    }
+
+    /// Returns the source span table of the index, or `None` if the index does
+    /// not contain a source span table.
+    pub fn source_spans(&self) -> Option<SourceSpanTable<'a>> {
+        self.iter().find_map(|entry| entry.as_source_spans(self))
+    }
+
+    /// Returns the proof source map of the index, or `None` if the index does
+    /// not contain a proof source map.
+    pub fn proof_source_map(&self) -> Option<ProofSourceMap<'a>> {
+        self.iter()
+            .find_map(|entry| entry.as_proof_source_map(self))
+    }
+
     /// Returns an iterator over all table entries in the index.
     pub fn iter(&self) -> EntryIterator<'a> {
         EntryIterator {
@@ -65,9 +104,58 @@ pub struct Entry {
     pub(crate) ptr: u64,
 }
 
-const NAME_TABLE_ID: u32 = 0x656d614e;
+/// The id of the name table, `"Name"`, which is defined by the MM0 format.
+pub(crate) const NAME_TABLE_ID: u32 = 0x656d614e;
+
+/// The id of the source span table, `"Span"`.
+pub(crate) const SOURCE_SPAN_TABLE_ID: u32 = 0x6e617053;
+
+/// The id of the proof source map, `"Step"`.
+pub(crate) const PROOF_SOURCE_MAP_ID: u32 = 0x70657453;
+
+/// The id of the sorted name order, `"Srtd"`.
+pub(crate) const SORTED_NAMES_ID: u32 = 0x64747253;
+
+/// An error that prevented a table from being written to the index.
+#[cfg(feature = "alloc")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WriteError {
+    /// A path or name that is stored as a nul-terminated string contains a nul
+    /// byte.
+    NulByte,
+    /// The file has no name table, which the table is built from.
+    NoNameTable,
+    /// An entry of the name table of the file cannot be read.
+    MalformedNameTable,
+    /// A location refers to a source file that was not added to the source
+    /// span table with `SourceSpanWriter::add_file`.
+    UnknownFile(u32),
+}
+
+/// The offset of the pointer to the index in the file header.
+#[cfg(feature = "alloc")]
+const INDEX_PTR_OFFSET: usize = 32;
 
 impl Entry {
+    /// Returns the id of the table, which determines how its data should be
+    /// interpreted.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Returns the pointer to the data of the table.
+    pub fn ptr(&self) -> u64 {
+        self.ptr
+    }
+
+    /// Returns whether this crate knows how to interpret the table.
+    pub(crate) fn is_known(&self) -> bool {
+        matches!(
+            self.id,
+            NAME_TABLE_ID | SOURCE_SPAN_TABLE_ID | PROOF_SOURCE_MAP_ID | SORTED_NAMES_ID
+        )
+    }
+
     /// If this table entry is a name table, return a `NameTable` object to the
     /// name table, or `None` otherwise.
     ///
@@ -83,14 +171,101 @@ impl Entry {
             .ok()?
             .1;
 
+        // Only use a sorted name order that covers the entire name table.
+        let order = index
+            .iter()
+            .filter(|entry| entry.id == SORTED_NAMES_ID)
+            .find_map(|entry| parser::parse_sorted_names(index.file, num, entry.ptr).ok())
+            .map(|(_, order)| order);
+
         Some(NameTable::new(
             index.num_sorts,
             index.num_terms,
             index.num_theorems,
             index.file,
             entries,
+            order,
+        ))
+    }
+
+    /// If this table entry is a source span table, return a `SourceSpanTable`
+    /// object to the source span table, or `None` otherwise.
+    pub fn as_source_spans<'a>(&self, index: &Index<'a>) -> Option<SourceSpanTable<'a>> {
+        if self.id != SOURCE_SPAN_TABLE_ID {
+            return None;
+        }
+
+        let num = index.num_sorts as u64 + index.num_terms as u64 + index.num_theorems as u64;
+        let (files, spans) = parser::parse_source_span_table(index.file, num, self.ptr)
+            .ok()?
+            .1;
+
+        Some(SourceSpanTable::new(
+            index.num_sorts,
+            index.num_terms,
+            index.num_theorems,
+            index.file,
+            files,
+            spans,
         ))
     }
+
+    /// If this table entry is a proof source map, return a `ProofSourceMap`
+    /// object to the proof source map, or `None` otherwise.
+    pub fn as_proof_source_map<'a>(&self, index: &Index<'a>) -> Option<ProofSourceMap<'a>> {
+        if self.id != PROOF_SOURCE_MAP_ID {
+            return None;
+        }
+
+        let entries = parser::parse_proof_source_map(index.file, self.ptr).ok()?.1;
+
+        Some(ProofSourceMap::new(index.file, entries))
+    }
+}
+
+/// Build a copy of a proof file with a table appended to it, and a new index
+/// appended after the table that replaces the tables with the same id.
+///
+/// The closure is given the pointer to the table and returns its content.
+#[cfg(feature = "alloc")]
+pub(crate) fn append_table<F>(mmb: &Mmb, id: u32, table: F) -> Vec<u8>
+where
+    F: FnOnce(u64) -> Vec<u8>,
+{
+    let align = |file: &mut Vec<u8>| {
+        let len = (file.len() + 7) & !7;
+        file.resize(len, 0);
+    };
+
+    let mut file = mmb.file().to_vec();
+
+    align(&mut file);
+    let table_ptr = file.len() as u64;
+    let table = table(table_ptr);
+    file.extend_from_slice(&table);
+
+    align(&mut file);
+    let index_ptr = file.len() as u64;
+
+    let entries: Vec<_> = mmb
+        .index()
+        .into_iter()
+        .flat_map(|index| index.iter())
+        .filter(|entry| entry.id != id)
+        .chain(core::iter::once(Entry { id, ptr: table_ptr }))
+        .collect();
+
+    file.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+
+    for entry in entries {
+        file.extend_from_slice(&entry.id.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&entry.ptr.to_le_bytes());
+    }
+
+    file[INDEX_PTR_OFFSET..INDEX_PTR_OFFSET + 8].copy_from_slice(&index_ptr.to_le_bytes());
+
+    file
 }
 
 impl<'a> Iterator for EntryIterator<'a> {
@@ -1,26 +1,57 @@
 /// The name table of the index defines the names and pointers to the
 /// declarations of sorts, terms and theorems in the proof file.
+///
+/// Lookups by name with `find` and `find_kind` are a binary search if the
+/// index also contains a sorted name order, and a linear scan of the name table
+/// otherwise. Files written by MM0 compilers have no sorted name order, so for
+/// repeated lookups, `sorted` builds a sorted index of the names in memory
+/// once. Alternatively, `write_sorted_names` adds a sorted name order to the
+/// file itself, with the id `"Srtd"` in the index.
+///
+/// The sorted name order starts with the number of entries as a `u64`, which
+/// must match the number of entries in the name table, followed by the
+/// positions of the entries in the name table as `u64`s, sorted by the bytes of
+/// their names. Positions count the sorts first, then the terms and then the
+/// theorems.
+#[derive(Copy, Clone)]
 pub struct NameTable<'a> {
     num_sorts: u8,
     num_terms: u32,
     num_theorems: u32,
 
-    file: &'a [u8],
+    file: parser::Window<'a>,
     entries: &'a [u8],
+    order: Option<&'a [u8]>,
 }
 
 /// A subsection of the name table containing only a single kind of entry.
 pub struct NameTableSection<'a> {
-    file: &'a [u8],
-    entries: &'a [u8],
+    pub(crate) file: parser::Window<'a>,
+    pub(crate) entries: &'a [u8],
 }
 
+/// The kind of item an entry in the name table refers to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Kind {
+    Sort,
+    Term,
+    Theorem,
+}
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::index::WriteError;
 use crate::parser;
+#[cfg(feature = "alloc")]
+use crate::Mmb;
 
 impl<'a> NameTableSection<'a> {
     /// Returns an entry of the name table by index, or `None` if the index is
     /// out of range.
-    pub fn get(&self, idx: u64) -> Option<Name<'_>> {
+    pub fn get(&self, idx: u64) -> Option<Name<'a>> {
         let name = parser::seek_name_entry(self.file, self.entries, idx).ok()?;
 
         Some(name.1)
@@ -40,8 +71,9 @@ impl<'a> NameTable<'a> {
         num_sorts: u8,
         num_terms: u32,
         num_theorems: u32,
-        file: &'a [u8],
+        file: parser::Window<'a>,
         entries: &'a [u8],
+        order: Option<&'a [u8]>,
     ) -> NameTable<'a> {
         NameTable {
             num_sorts,
@@ -49,11 +81,109 @@ impl<'a> NameTable<'a> {
             num_theorems,
             file,
             entries,
+            order,
+        }
+    }
+
+    /// Returns whether the index contains a sorted name order for this table,
+    /// which makes `find` and `find_kind` logarithmic instead of linear.
+    pub fn is_sorted(&self) -> bool {
+        self.order.is_some()
+    }
+
+    /// Returns the kind, index and entry of the item with the given name, or
+    /// `None` if there is no such item. If several items have the name, the
+    /// first one in the order sorts, terms, theorems is returned.
+    ///
+    /// This is a binary search if the index contains a sorted name order, and
+    /// a linear scan of the name table otherwise, which is the case for files
+    /// written by MM0 compilers. Use `sorted` for repeated lookups in those.
+    ///
+    /// A sorted name order that is not actually sorted can cause names to be
+    /// missed, but never returns an item with a different name.
+    pub fn find(&self, name: &[u8]) -> Option<(Kind, u64, Name<'a>)> {
+        self.matches(self.order, name).first()
+    }
+
+    /// Returns the index and entry of the item of the given kind with the
+    /// given name, or `None` if there is no such item.
+    ///
+    /// Like `find`, this is a linear scan of the name table unless the index
+    /// contains a sorted name order.
+    pub fn find_kind(&self, kind: Kind, name: &[u8]) -> Option<(u64, Name<'a>)> {
+        self.matches(self.order, name).first_of_kind(kind)
+    }
+
+    /// Build a sorted index of the names in memory, for logarithmic lookups in
+    /// files without a sorted name order.
+    ///
+    /// Building the index reads and sorts all names, so it pays off for more
+    /// than a few lookups. Names after an entry that cannot be read are not
+    /// part of the index.
+    #[cfg(feature = "alloc")]
+    pub fn sorted(&self) -> SortedNames<'a> {
+        let mut order: Vec<_> = self.iter().zip(0u64..).collect();
+        order.sort_by(|(a, _), (b, _)| a.name.cmp(b.name));
+
+        SortedNames {
+            table: *self,
+            positions: order.into_iter().map(|(_, position)| position).collect(),
+        }
+    }
+
+    /// Returns an iterator over the items with the given name, using the given
+    /// sorted order of positions if possible.
+    fn matches<'b, P: Positions>(&self, order: Option<P>, name: &'b [u8]) -> Matches<'a, 'b, P> {
+        let sorted = order.and_then(|order| {
+            // Find the first position in the sorted order whose name is not
+            // less than the given name.
+            let (mut low, mut high) = (0, order.len());
+
+            while low < high {
+                let mid = low + (high - low) / 2;
+
+                if self.sorted_entry(&order, mid)?.name < name {
+                    low = mid + 1;
+                } else {
+                    high = mid;
+                }
+            }
+
+            Some((order, low))
+        });
+
+        Matches {
+            table: *self,
+            name,
+            sorted,
+            position: 0,
+        }
+    }
+
+    fn sorted_entry<P: Positions>(&self, order: &P, idx: u64) -> Option<Name<'a>> {
+        let position = order.get(idx)?;
+        let (_, name) = parser::seek_name_entry(self.file, self.entries, position).ok()?;
+
+        Some(name)
+    }
+
+    /// Returns the kind and index of the item at the given position in the
+    /// name table.
+    fn item(&self, position: u64) -> (Kind, u64) {
+        let sorts = self.num_sorts as u64;
+        let terms = sorts + self.num_terms as u64;
+
+        if position < sorts {
+            (Kind::Sort, position)
+        } else if position < terms {
+            (Kind::Term, position - sorts)
+        } else {
+            (Kind::Theorem, position - terms)
         }
     }
 
     /// Returns the subsection of the name table containing the sorts.
-    pub fn sorts(&self) -> NameTableSection<'_> {
+    pub fn sorts(&self) -> NameTableSection<'a> {
         let from = 0;
         let len = self.num_sorts as u64;
 
@@ -61,7 +191,7 @@ impl<'a> NameTable<'a> {
     }
 
     /// Returns the subsection of the name table containing the terms.
-    pub fn terms(&self) -> NameTableSection<'_> {
+    pub fn terms(&self) -> NameTableSection<'a> {
         let from = self.num_sorts as u64;
         let len = self.num_terms as u64;
 
@@ -69,13 +199,23 @@ impl<'a> NameTable<'a> {
     }
 
     /// Returns the subsection of the name table containing the theorems.
-    pub fn theorems(&self) -> NameTableSection<'_> {
+    pub fn theorems(&self) -> NameTableSection<'a> {
         let from = self.num_sorts as u64 + self.num_terms as u64;
         let len = self.num_theorems as u64;
 
         self.kind(from, len)
     }
 
+    /// Returns the subsection of the name table containing the given kind of
+    /// entries.
+    pub fn section(&self, kind: Kind) -> NameTableSection<'a> {
+        match kind {
+            Kind::Sort => self.sorts(),
+            Kind::Term => self.terms(),
+            Kind::Theorem => self.theorems(),
+        }
+    }
+
     /// Returns an iterator over all entries in the entire name table.
     pub fn iter(&self) -> NameIterator<'a> {
         NameIterator {
@@ -84,11 +224,9 @@ impl<'a> NameTable<'a> {
         }
     }
 
-    fn kind(&self, from: u64, len: u64) -> NameTableSection<'_> {
+    fn kind(&self, from: u64, len: u64) -> NameTableSection<'a> {
         let entries = parser::subslice_name_table(self.entries, from, len)
-            .ok()
-            .unwrap()
-            .1;
+            .map_or(&[][..], |(_, entries)| entries);
 
         NameTableSection {
             file: self.file,
@@ -133,9 +271,190 @@ impl<'a> IntoIterator for &NameTableSection<'a> {
     }
 }
 
+/// Build a copy of a proof file with a sorted name order for its name table
+/// added to its index.
+///
+/// The sorted name order and a new index are appended to the end of the file,
+/// so all pointers into the existing file stay valid. An existing sorted name
+/// order is replaced.
+///
+/// Returns `WriteError::NoNameTable` if the file has no name table, and
+/// `WriteError::MalformedNameTable` if an entry of the name table cannot be
+/// read, since the sorted name order must cover every entry.
+#[cfg(feature = "alloc")]
+pub fn write_sorted_names(mmb: &Mmb) -> Result<Vec<u8>, WriteError> {
+    let names = mmb
+        .index()
+        .and_then(|index| index.name_table())
+        .ok_or(WriteError::NoNameTable)?;
+
+    let mut order: Vec<_> = names.iter().map(|name| name.name).zip(0u64..).collect();
+
+    let num = names.num_sorts as u64 + names.num_terms as u64 + names.num_theorems as u64;
+
+    if order.len() as u64 != num {
+        return Err(WriteError::MalformedNameTable);
+    }
+
+    order.sort();
+
+    let file = crate::index::append_table(mmb, crate::index::SORTED_NAMES_ID, |_| {
+        let mut table = Vec::with_capacity(8 + 8 * order.len());
+        table.extend_from_slice(&(order.len() as u64).to_le_bytes());
+
+        for (_, position) in order {
+            table.extend_from_slice(&position.to_le_bytes());
+        }
+
+        table
+    });
+
+    Ok(file)
+}
+
+/// A sorted index of the names of a name table, built in memory by
+/// `NameTable::sorted`.
+///
+/// Lookups are a binary search over the positions of the entries in the name
+/// table, sorted by the bytes of their names, and return the same items as the
+/// lookups of the name table.
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+pub struct SortedNames<'a> {
+    table: NameTable<'a>,
+    positions: Vec<u64>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> SortedNames<'a> {
+    /// Returns the kind, index and entry of the item with the given name, or
+    /// `None` if there is no such item, like `NameTable::find`.
+    pub fn find(&self, name: &[u8]) -> Option<(Kind, u64, Name<'a>)> {
+        self.table.matches(Some(&self.positions[..]), name).first()
+    }
+
+    /// Returns the index and entry of the item of the given kind with the
+    /// given name, or `None` if there is no such item, like
+    /// `NameTable::find_kind`.
+    pub fn find_kind(&self, kind: Kind, name: &[u8]) -> Option<(u64, Name<'a>)> {
+        self.table
+            .matches(Some(&self.positions[..]), name)
+            .first_of_kind(kind)
+    }
+}
+
+/// A sorted order of positions in the name table.
+trait Positions {
+    fn len(&self) -> u64;
+
+    fn get(&self, idx: u64) -> Option<u64>;
+}
+
+/// The sorted name order of the index, as stored in the file.
+impl Positions for &[u8] {
+    fn len(&self) -> u64 {
+        <[u8]>::len(self) as u64 / 8
+    }
+
+    fn get(&self, idx: u64) -> Option<u64> {
+        let (_, position) = parser::seek_sorted_name(self, idx).ok()?;
+
+        Some(position)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Positions for &[u64] {
+    fn len(&self) -> u64 {
+        <[u64]>::len(self) as u64
+    }
+
+    fn get(&self, idx: u64) -> Option<u64> {
+        <[u64]>::get(self, idx as usize).copied()
+    }
+}
+
+/// An iterator over the items with a given name.
+///
+/// With a sorted order, the iterator walks the sorted order from the first
+/// candidate for as long as the names match. Otherwise, it scans the entire
+/// name table.
+struct Matches<'a, 'b, P> {
+    table: NameTable<'a>,
+    name: &'b [u8],
+    sorted: Option<(P, u64)>,
+    position: u64,
+}
+
+impl<'a, 'b, P: Positions> Matches<'a, 'b, P> {
+    /// Returns the first item in the order sorts, terms, theorems.
+    ///
+    /// A linear scan finds it first, but a sorted order that was not written
+    /// by this crate may list items with the same name in any order.
+    fn first(mut self) -> Option<(Kind, u64, Name<'a>)> {
+        if self.sorted.is_some() {
+            self.min_by_key(|&(kind, idx, _)| (kind, idx))
+        } else {
+            self.next()
+        }
+    }
+
+    fn first_of_kind(self, kind: Kind) -> Option<(u64, Name<'a>)> {
+        let sorted = self.sorted.is_some();
+        let mut matches = self.filter(|&(k, _, _)| k == kind);
+
+        let found = if sorted {
+            matches.min_by_key(|&(_, idx, _)| idx)
+        } else {
+            matches.next()
+        };
+
+        found.map(|(_, idx, name)| (idx, name))
+    }
+}
+
+impl<'a, 'b, P: Positions> Iterator for Matches<'a, 'b, P> {
+    type Item = (Kind, u64, Name<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let table = &self.table;
+
+        match &mut self.sorted {
+            Some((order, idx)) => {
+                let position = order.get(*idx)?;
+                let (_, name) =
+                    parser::seek_name_entry(table.file, table.entries, position).ok()?;
+
+                if name.name != self.name {
+                    return None;
+                }
+
+                *idx += 1;
+
+                let (kind, idx) = table.item(position);
+
+                Some((kind, idx, name))
+            }
+            None => loop {
+                let position = self.position;
+                let (_, name) =
+                    parser::seek_name_entry(table.file, table.entries, position).ok()?;
+
+                self.position += 1;
+
+                if name.name == self.name {
+                    let (kind, idx) = table.item(position);
+
+                    return Some((kind, idx, name));
+                }
+            },
+        }
+    }
+}
+
 /// An iterator over entries in the name table.
 pub struct NameIterator<'a> {
-    file: &'a [u8],
+    file: parser::Window<'a>,
     entries: &'a [u8],
 }
 
@@ -143,9 +462,17 @@ pub struct NameIterator<'a> {
 ///
 /// Entries in the name table contain a pointer to the declaration of the item
 /// in the proof stream and a nul-terminated string of the name of the item.
+///
+/// With the `serde` feature, the name is serialized as a string if it is valid
+/// UTF-8, and as a byte string otherwise. Deserializing borrows the name from
+/// the input, which works for strings without escape sequences, such as the
+/// names of MM0 identifiers in JSON, and for byte strings in binary formats.
+/// Strings with escape sequences are rejected.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Name<'a> {
     pub ptr: u64,
+    #[cfg_attr(feature = "serde", serde(borrow, with = "crate::serialize::bytes"))]
     pub name: &'a [u8],
 }
 
@@ -162,11 +489,167 @@ impl<'a> Iterator for NameIterator<'a> {
 }
 
 impl<'a> Name<'a> {
-    pub fn to_str(&self) -> Result<&str, &[u8]> {
-        if self.name.len() == 0 {
+    pub fn to_str(&self) -> Result<&'a str, &'a [u8]> {
+        if self.name.is_empty() {
             Err(&[])
         } else {
-            std::str::from_utf8(self.name).map_err(|_| self.name)
+            core::str::from_utf8(self.name).map_err(|_| self.name)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::index::WriteError;
+    use crate::testing::{self, Builder};
+    use crate::Mmb;
+
+    fn file() -> Vec<u8> {
+        let mut builder = Builder::new();
+        let wff = builder.sort("wff");
+        builder.sort("nat");
+        builder.term("im", wff, &[0, 0]);
+        builder.term("wff", wff, &[]);
+        builder.term("an", wff, &[0, 0]);
+        builder.axiom("ax", &[], alloc::vec![0], Vec::new());
+        builder.axiom("im", &[], alloc::vec![0], Vec::new());
+
+        builder.build()
+    }
+
+    fn lookups<'a, F, K>(find: F, find_kind: K) -> Vec<Option<(Kind, u64)>>
+    where
+        F: Fn(&[u8]) -> Option<(Kind, u64, Name<'a>)>,
+        K: Fn(Kind, &[u8]) -> Option<(u64, Name<'a>)>,
+    {
+        let mut found = Vec::new();
+
+        for name in &["wff", "nat", "im", "an", "ax", "missing", ""] {
+            found.push(find(name.as_bytes()).map(|(kind, idx, _)| (kind, idx)));
+
+            for &kind in &[Kind::Sort, Kind::Term, Kind::Theorem] {
+                found.push(find_kind(kind, name.as_bytes()).map(|(idx, _)| (kind, idx)));
+            }
         }
+
+        found
+    }
+
+    fn table_lookups(names: &NameTable) -> Vec<Option<(Kind, u64)>> {
+        lookups(
+            |name| names.find(name),
+            |kind, name| names.find_kind(kind, name),
+        )
+    }
+
+    #[test]
+    fn sorted_lookups_match_linear_lookups() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+        let names = mmb.index().unwrap().name_table().unwrap();
+        assert!(!names.is_sorted());
+
+        let sorted_file = write_sorted_names(&mmb).unwrap();
+        let sorted_mmb = Mmb::from(&sorted_file).unwrap();
+        let sorted = sorted_mmb.index().unwrap().name_table().unwrap();
+        assert!(sorted.is_sorted());
+
+        assert_eq!(table_lookups(&sorted), table_lookups(&names));
+        assert_eq!(
+            sorted.find(b"wff").map(|(k, i, _)| (k, i)),
+            Some((Kind::Sort, 0))
+        );
+        assert_eq!(
+            sorted.find(b"im").map(|(k, i, _)| (k, i)),
+            Some((Kind::Term, 0))
+        );
+        assert_eq!(
+            sorted.find_kind(Kind::Theorem, b"im").map(|(i, _)| i),
+            Some(1)
+        );
+        assert_eq!(sorted.find(b"missing").map(|(k, i, _)| (k, i)), None);
+    }
+
+    #[test]
+    fn in_memory_lookups_match_linear_lookups() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+        let names = mmb.index().unwrap().name_table().unwrap();
+        let sorted = names.sorted();
+
+        assert_eq!(
+            lookups(
+                |name| sorted.find(name),
+                |kind, name| sorted.find_kind(kind, name)
+            ),
+            table_lookups(&names)
+        );
+        assert_eq!(
+            sorted.find(b"im").map(|(k, i, _)| (k, i)),
+            Some((Kind::Term, 0))
+        );
+        assert_eq!(
+            sorted.find_kind(Kind::Theorem, b"im").map(|(i, _)| i),
+            Some(1)
+        );
+        assert_eq!(sorted.find(b"wff").unwrap().2.name, b"wff");
+    }
+
+    #[test]
+    fn in_memory_index_skips_unreadable_names() {
+        let mut file = file();
+
+        // Point the name of the last entry, the theorem `im`, past the end of
+        // the file.
+        let last = testing::name_entry(&file, 6) + 8;
+        file[last..last + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let mmb = Mmb::from(&file).unwrap();
+        let sorted = mmb.index().unwrap().name_table().unwrap().sorted();
+
+        assert_eq!(sorted.find_kind(Kind::Term, b"im").map(|(i, _)| i), Some(0));
+        assert_eq!(sorted.find_kind(Kind::Theorem, b"im"), None);
+    }
+
+    #[test]
+    fn writing_again_replaces_the_order() {
+        let file = file();
+        let first = write_sorted_names(&Mmb::from(&file).unwrap()).unwrap();
+        let second = write_sorted_names(&Mmb::from(&first).unwrap()).unwrap();
+
+        let mmb = Mmb::from(&second).unwrap();
+        let index = mmb.index().unwrap();
+
+        assert_eq!(index.iter().count(), 2);
+        assert!(index.name_table().unwrap().is_sorted());
+    }
+
+    #[test]
+    fn files_without_name_table_are_rejected() {
+        let mut file = file();
+        let index_ptr = testing::index_ptr(&file);
+        file[index_ptr + 8..index_ptr + 12].copy_from_slice(b"None");
+
+        assert_eq!(
+            write_sorted_names(&Mmb::from(&file).unwrap()),
+            Err(WriteError::NoNameTable)
+        );
+    }
+
+    #[test]
+    fn malformed_name_tables_are_rejected() {
+        let mut file = file();
+
+        // Point the name of the last entry past the end of the file.
+        let last = testing::name_entry(&file, 6) + 8;
+        file[last..last + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        assert_eq!(
+            write_sorted_names(&Mmb::from(&file).unwrap()),
+            Err(WriteError::MalformedNameTable)
+        );
     }
 }
@@ -0,0 +1,380 @@
+/// The proof source map of the index maps commands of the proof streams to the
+/// locations in the source files of the steps that produced them.
+///
+/// The table starts with the number of entries as a `u64`, followed by the
+/// entries sorted by offset. Each entry contains the offset of a command
+/// relative to the start of the proof section as a `u64`, the file id, line
+/// and column of the step as `u32`s followed by 4 bytes of padding, and a
+/// pointer to the nul-terminated name of the step, or zero if the step has no
+/// name. An entry applies to all commands from its offset up to the offset of
+/// the next entry. Ranges of commands without a location start with an entry
+/// with the file id `u32::MAX`, and so does the end of every statement that
+/// contains a step, unless the next step starts there.
+///
+/// File ids refer to the source files of the source span table.
+///
+/// The proof source map has the id `"Step"` in the index.
+pub struct ProofSourceMap<'a> {
+    file: parser::Window<'a>,
+    entries: &'a [u8],
+}
+
+/// The step of a proof that produced a range of commands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProofStep<'a> {
+    /// The offset of the first command produced by the step, relative to the
+    /// start of the proof section.
+    pub offset: u64,
+    /// The location of the step in the source files.
+    pub span: SourceSpan,
+    /// The name of the step, such as the name of a `have` step.
+    ///
+    /// With the `serde` feature, the name is serialized like the name of a
+    /// `Name`, and deserializing borrows it from the input in the same way:
+    /// strings with escape sequences, such as `"h\u0031"` in JSON, are
+    /// rejected.
+    #[cfg_attr(
+        feature = "serde",
+        serde(borrow, with = "crate::serialize::option_bytes")
+    )]
+    pub name: Option<&'a [u8]>,
+}
+
+use crate::index::source_spans::{SourceSpan, NO_SPAN};
+use crate::parser;
+
+pub(crate) const ENTRY_SIZE: u64 = 32;
+
+impl<'a> ProofSourceMap<'a> {
+    pub(crate) fn new(file: parser::Window<'a>, entries: &'a [u8]) -> ProofSourceMap<'a> {
+        ProofSourceMap { file, entries }
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> u64 {
+        self.entries.len() as u64 / ENTRY_SIZE
+    }
+
+    /// Returns whether the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the step that produced the command at the given offset, as
+    /// reported by `ProofCommandIter`, or `None` if the command has no
+    /// location. Offsets past the end of the statement of a step, including
+    /// offsets past the end of the proof section, have no location.
+    pub fn get(&self, offset: usize) -> Option<ProofStep<'a>> {
+        let offset = offset as u64;
+
+        // Find the number of entries starting at or before the offset.
+        let (mut low, mut high) = (0, self.len());
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (_, start) = parser::seek_proof_step_offset(self.entries, mid).ok()?;
+
+            if start <= offset {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        let (_, step) =
+            parser::seek_proof_step(self.file, self.entries, low.checked_sub(1)?).ok()?;
+
+        Some(step).filter(|step| step.span.file != NO_SPAN)
+    }
+
+    /// Returns an iterator over all steps with a location.
+    pub fn iter(&self) -> ProofStepIterator<'a> {
+        ProofStepIterator {
+            file: self.file,
+            entries: self.entries,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &ProofSourceMap<'a> {
+    type Item = ProofStep<'a>;
+    type IntoIter = ProofStepIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of the proof source map.
+pub struct ProofStepIterator<'a> {
+    file: parser::Window<'a>,
+    entries: &'a [u8],
+}
+
+impl<'a> Iterator for ProofStepIterator<'a> {
+    type Item = ProofStep<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (left, step) = parser::parse_proof_step(self.file, self.entries).ok()?;
+
+            self.entries = left;
+
+            if step.span.file != NO_SPAN {
+                return Some(step);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub use self::writer::ProofSourceMapWriter;
+
+#[cfg(feature = "alloc")]
+mod writer {
+    use alloc::vec::Vec;
+
+    use super::ENTRY_SIZE;
+    use crate::index::source_spans::{SourceSpan, NO_SPAN};
+    use crate::index::{self, WriteError, PROOF_SOURCE_MAP_ID};
+    use crate::Mmb;
+
+    /// A builder for the proof source map of a proof file.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct ProofSourceMapWriter {
+        steps: Vec<(u64, Option<SourceSpan>, Option<Vec<u8>>)>,
+    }
+
+    impl ProofSourceMapWriter {
+        /// Create a builder without any steps.
+        pub fn new() -> ProofSourceMapWriter {
+            ProofSourceMapWriter::default()
+        }
+
+        /// Add a step that produced the commands starting at the given offset,
+        /// up to the offset of the next step or range without location.
+        ///
+        /// The name is stored as a nul-terminated string, so names containing
+        /// nul bytes are rejected with `WriteError::NulByte`.
+        pub fn push(
+            &mut self,
+            offset: usize,
+            span: SourceSpan,
+            name: Option<&[u8]>,
+        ) -> Result<(), WriteError> {
+            if matches!(name, Some(name) if name.contains(&0)) {
+                return Err(WriteError::NulByte);
+            }
+
+            self.steps
+                .push((offset as u64, Some(span), name.map(|name| name.to_vec())));
+
+            Ok(())
+        }
+
+        /// Add a range of commands without location starting at the given
+        /// offset, up to the offset of the next step.
+        pub fn push_unknown(&mut self, offset: usize) {
+            self.steps.push((offset as u64, None, None));
+        }
+
+        /// Build a copy of a proof file with this proof source map added to its
+        /// index.
+        ///
+        /// This works like `SourceSpanWriter::write`, and replaces an existing
+        /// proof source map. Steps may be added in any order, and of several
+        /// steps with the same offset, only the last one added is kept. The
+        /// range of the last step of a statement ends with the statement.
+        pub fn write(&self, mmb: &Mmb) -> Vec<u8> {
+            let mut sorted: Vec<_> = self.steps.iter().rev().collect();
+            sorted.sort_by_key(|(offset, _, _)| *offset);
+            sorted.dedup_by_key(|(offset, _, _)| *offset);
+
+            let ends: Vec<u64> = mmb
+                .statements()
+                .map(|statement| (statement.offset + statement.data.len()) as u64)
+                .collect();
+
+            let mut steps = Vec::with_capacity(sorted.len());
+
+            for (i, &(offset, span, name)) in sorted.iter().enumerate() {
+                steps.push((*offset, *span, name.as_deref()));
+
+                if span.is_none() {
+                    continue;
+                }
+
+                let statement = match ends.binary_search(offset) {
+                    Ok(i) => i + 1,
+                    Err(i) => i,
+                };
+
+                if let Some(&end) = ends.get(statement) {
+                    if !matches!(sorted.get(i + 1), Some((next, _, _)) if *next <= end) {
+                        steps.push((end, None, None));
+                    }
+                }
+            }
+
+            index::append_table(mmb, PROOF_SOURCE_MAP_ID, |ptr| {
+                let mut name_ptr = ptr + 8 + ENTRY_SIZE * steps.len() as u64;
+
+                let mut table = Vec::new();
+                table.extend_from_slice(&(steps.len() as u64).to_le_bytes());
+
+                for (offset, span, name) in &steps {
+                    let (file, line, column) = match span {
+                        Some(span) => (span.file, span.line, span.column),
+                        None => (NO_SPAN, 0, 0),
+                    };
+
+                    let ptr = match name {
+                        Some(name) => {
+                            let ptr = name_ptr;
+                            name_ptr += name.len() as u64 + 1;
+                            ptr
+                        }
+                        None => 0,
+                    };
+
+                    table.extend_from_slice(&offset.to_le_bytes());
+                    table.extend_from_slice(&file.to_le_bytes());
+                    table.extend_from_slice(&line.to_le_bytes());
+                    table.extend_from_slice(&column.to_le_bytes());
+                    table.extend_from_slice(&0u32.to_le_bytes());
+                    table.extend_from_slice(&ptr.to_le_bytes());
+                }
+
+                for (_, _, name) in &steps {
+                    if let Some(name) = name {
+                        table.extend_from_slice(name);
+                        table.push(0);
+                    }
+                }
+
+                table
+            })
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::index::WriteError;
+    use crate::opcode::Proof;
+    use crate::testing;
+    use crate::Mmb;
+
+    fn span(line: u32) -> SourceSpan {
+        SourceSpan {
+            file: 0,
+            line,
+            column: 1,
+        }
+    }
+
+    fn step(offset: u64, line: u32, name: Option<&[u8]>) -> ProofStep<'_> {
+        ProofStep {
+            offset,
+            span: span(line),
+            name,
+        }
+    }
+
+    /// Returns a proof file with the statements of `testing::logic`, which end
+    /// at offset 20, followed by two theorems at offsets 20 and 36, each with
+    /// a proof of ten commands starting 5 bytes into the statement.
+    fn file() -> Vec<u8> {
+        let mut builder = testing::logic();
+        let proof = testing::proof(&[(Proof::Ref, 0); 10]);
+
+        builder.theorem("a", &[0], testing::unify(&[]), proof.clone());
+        builder.theorem("b", &[0], testing::unify(&[]), proof);
+
+        builder.build()
+    }
+
+    #[test]
+    fn written_steps_are_read_back() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+
+        let mut writer = ProofSourceMapWriter::new();
+        writer.push(30, span(3), None).unwrap();
+        writer.push(25, span(1), Some(b"h1")).unwrap();
+        writer.push_unknown(32);
+        writer.push(34, span(6), None).unwrap();
+        writer.push(41, span(5), Some(b"h2")).unwrap();
+        // Replaces the step added before at the same offset.
+        writer.push(30, span(4), Some(b"h3")).unwrap();
+
+        let written = writer.write(&mmb);
+        let mmb = Mmb::from(&written).unwrap();
+        let index = mmb.index().unwrap();
+        let map = index.proof_source_map().unwrap();
+
+        // The ends of both theorems are added as ranges without location.
+        assert_eq!(map.len(), 7);
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [
+                step(25, 1, Some(b"h1")),
+                step(30, 4, Some(b"h3")),
+                step(34, 6, None),
+                step(41, 5, Some(b"h2")),
+            ]
+        );
+
+        assert_eq!(map.get(0), None);
+        assert_eq!(map.get(24), None);
+        assert_eq!(map.get(25), Some(step(25, 1, Some(b"h1"))));
+        assert_eq!(map.get(29), Some(step(25, 1, Some(b"h1"))));
+        assert_eq!(map.get(31), Some(step(30, 4, Some(b"h3"))));
+        assert_eq!(map.get(32), None);
+        assert_eq!(map.get(35), Some(step(34, 6, None)));
+        assert_eq!(map.get(36), None);
+        assert_eq!(map.get(40), None);
+        assert_eq!(map.get(51), Some(step(41, 5, Some(b"h2"))));
+        assert_eq!(map.get(52), None);
+        assert_eq!(map.get(1000), None);
+
+        // The name table of the original index is kept.
+        assert!(index.name_table().is_some());
+    }
+
+    #[test]
+    fn steps_followed_by_the_next_statement() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+
+        let mut writer = ProofSourceMapWriter::new();
+        writer.push(25, span(1), None).unwrap();
+        writer.push(36, span(2), None).unwrap();
+        writer.push_unknown(52);
+
+        let written = writer.write(&mmb);
+        let mmb = Mmb::from(&written).unwrap();
+        let map = mmb.index().unwrap().proof_source_map().unwrap();
+
+        // No end is added where the next entry starts.
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(35), Some(step(25, 1, None)));
+        assert_eq!(map.get(36), Some(step(36, 2, None)));
+        assert_eq!(map.get(52), None);
+    }
+
+    #[test]
+    fn names_with_nul_bytes_are_rejected() {
+        let mut writer = ProofSourceMapWriter::new();
+
+        assert_eq!(
+            writer.push(0, span(1), Some(b"h\0")),
+            Err(WriteError::NulByte)
+        );
+        assert_eq!(writer, ProofSourceMapWriter::new());
+    }
+}
@@ -0,0 +1,245 @@
+use alloc::vec::Vec;
+
+use crate::index::name_table::{Kind, Name, NameTable};
+
+impl<'a> NameTable<'a> {
+    /// Search the sorts, terms and theorems for names matching a pattern, and
+    /// return their kind, index and entry, best matches first.
+    ///
+    /// If the pattern contains `*` or `?`, it is matched as a glob, where `*`
+    /// matches any sequence of bytes and `?` any single byte. Otherwise, names
+    /// are ranked as exact matches first, then names starting with the
+    /// pattern, then names containing it, and finally names within a small
+    /// edit distance of the pattern, which is a third of its length but at
+    /// least one. Within each group, shorter names and closer matches come
+    /// first, and ties are broken by kind and index.
+    ///
+    /// Names are compared byte by byte, so the search is case sensitive. An
+    /// empty pattern matches no names; use `*` to list all of them.
+    pub fn search(&self, pattern: &[u8]) -> Vec<(Kind, u64, Name<'a>)> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+
+        let glob = pattern.iter().any(|&c| c == b'*' || c == b'?');
+        let max_distance = core::cmp::max(1, pattern.len() / 3);
+
+        let mut matches = Vec::new();
+
+        for &kind in &[Kind::Sort, Kind::Term, Kind::Theorem] {
+            for (name, idx) in self.section(kind).iter().zip(0..) {
+                let rank = if glob {
+                    Some((0, 0)).filter(|_| glob_match(pattern, name.name))
+                } else {
+                    rank(pattern, name.name, max_distance)
+                };
+
+                if let Some(rank) = rank {
+                    matches.push((rank, name.name.len(), kind, idx, name));
+                }
+            }
+        }
+
+        matches.sort_by_key(|&(rank, len, kind, idx, _)| (rank, len, kind, idx));
+
+        matches
+            .into_iter()
+            .map(|(_, _, kind, idx, name)| (kind, idx, name))
+            .collect()
+    }
+}
+
+/// Rank a name against a pattern as a group and a score within the group,
+/// where lower is better, or return `None` if the name does not match.
+fn rank(pattern: &[u8], name: &[u8], max_distance: usize) -> Option<(u8, usize)> {
+    if name == pattern {
+        return Some((0, 0));
+    }
+
+    if name.starts_with(pattern) {
+        return Some((1, 0));
+    }
+
+    if let Some(position) = find(name, pattern) {
+        return Some((2, position));
+    }
+
+    edit_distance(pattern, name, max_distance).map(|distance| (3, distance))
+}
+
+/// Returns the position of the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Returns the edit distance between two strings, counting insertions,
+/// deletions, substitutions and transpositions of adjacent bytes, or `None` if
+/// it is larger than `max`.
+fn edit_distance(a: &[u8], b: &[u8], max: usize) -> Option<usize> {
+    let difference = if a.len() > b.len() {
+        a.len() - b.len()
+    } else {
+        b.len() - a.len()
+    };
+
+    if difference > max {
+        return None;
+    }
+
+    // The distances between the prefixes of `a` and the previous two prefixes
+    // of `b`.
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=a.len()).collect();
+
+    for (j, &cb) in b.iter().enumerate() {
+        let mut row = Vec::with_capacity(a.len() + 1);
+        row.push(j + 1);
+
+        for (i, &ca) in a.iter().enumerate() {
+            let substitution = previous[i] + (ca != cb) as usize;
+            let deletion = previous[i + 1] + 1;
+            let insertion = row[i] + 1;
+
+            let mut distance = substitution.min(deletion).min(insertion);
+
+            if i > 0 && j > 0 && ca == b[j - 1] && a[i - 1] == cb {
+                distance = distance.min(before[i - 1] + 1);
+            }
+
+            row.push(distance);
+        }
+
+        // Every later distance is at least the minimum of this row.
+        if row.iter().all(|&distance| distance > max) {
+            return None;
+        }
+
+        before = core::mem::replace(&mut previous, row);
+    }
+
+    Some(previous[a.len()]).filter(|&distance| distance <= max)
+}
+
+/// Match a name against a glob pattern, where `*` matches any sequence of bytes
+/// and `?` any single byte.
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+
+    // The position of the last `*` in the pattern, and the position in the name
+    // it was tried at.
+    let mut star = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` match one more byte and try again.
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::testing::Builder;
+    use crate::Mmb;
+
+    /// Build a file with a sort `wff` and a term of that sort for each name.
+    fn file(names: &[&'static str]) -> Vec<u8> {
+        let mut builder = Builder::new();
+        let wff = builder.sort("wff");
+
+        for name in names {
+            builder.term(name, wff, &[]);
+        }
+
+        builder.build()
+    }
+
+    fn search(names: &[&'static str], pattern: &str) -> Vec<&'static str> {
+        let file = file(names);
+        let mmb = Mmb::from(&file).unwrap();
+        let table = mmb.index().unwrap().name_table().unwrap();
+
+        table
+            .search(pattern.as_bytes())
+            .into_iter()
+            .map(|(_, _, name)| {
+                let name = name.to_str().unwrap();
+                *names.iter().chain(&["wff"]).find(|&&n| n == name).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn groups_are_ranked_in_order() {
+        let names = ["am", "ban", "anx", "an", "xyz", "ann", "a", "bna"];
+
+        assert_eq!(search(&names, "an"), ["an", "anx", "ann", "ban", "a", "am"]);
+    }
+
+    #[test]
+    fn fuzzy_matches_allow_a_third_of_the_pattern() {
+        let names = ["mpbiran2", "mpbir2an", "mpbi", "syl"];
+
+        assert_eq!(search(&names, "mpbir2and"), ["mpbir2an", "mpbiran2"]);
+        assert_eq!(search(&names, "sly"), ["syl"]);
+        assert_eq!(search(&names, "abc"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn transpositions_count_as_one_edit() {
+        let names = ["ax", "bx"];
+
+        assert_eq!(search(&names, "xa"), ["ax"]);
+        assert_eq!(edit_distance(b"abcd", b"badc", 4), Some(2));
+        assert_eq!(edit_distance(b"abcd", b"badc", 1), None);
+    }
+
+    #[test]
+    fn globs_backtrack() {
+        let names = ["abc", "aXbYbZc", "abcbc", "abcd", "acb", "bc"];
+
+        assert_eq!(search(&names, "a*b*c"), ["abc", "abcbc", "aXbYbZc"]);
+        assert!(glob_match(b"a*bc", b"abxbc"));
+        assert!(!glob_match(b"a*bc", b"abxbcd"));
+    }
+
+    #[test]
+    fn globs_with_trailing_star() {
+        let names = ["ab", "abc", "a", "xab"];
+
+        assert_eq!(search(&names, "ab*"), ["ab", "abc"]);
+        assert_eq!(search(&names, "?b*"), ["ab", "abc"]);
+        assert_eq!(search(&names, "*"), ["a", "ab", "wff", "abc", "xab"]);
+    }
+
+    #[test]
+    fn empty_pattern_matches_nothing() {
+        assert_eq!(search(&["a", "b"], ""), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,397 @@
+/// The source span table of the index maps the sorts, terms and theorems of the
+/// proof file to their locations in the source files they were compiled from.
+///
+/// The table starts with the number of source files as a `u32` followed by 4
+/// bytes of padding, and a list of pointers to the nul-terminated paths of the
+/// source files, 8 bytes each. This is followed by one entry per sort, term and
+/// theorem, in this order, each containing the file id, line and column of the
+/// item as `u32`s followed by 4 bytes of padding. Items without a location
+/// have the file id `u32::MAX`.
+///
+/// The source span table has the id `"Span"` in the index.
+pub struct SourceSpanTable<'a> {
+    num_sorts: u8,
+    num_terms: u32,
+    num_theorems: u32,
+
+    file: parser::Window<'a>,
+    files: &'a [u8],
+    spans: &'a [u8],
+}
+
+/// The location of an item in the source file it was compiled from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceSpan {
+    /// The id of the source file, which is its position in the list of source
+    /// files of the table.
+    pub file: u32,
+    /// The line of the start of the declaration, starting at 1.
+    pub line: u32,
+    /// The column of the start of the declaration, starting at 1.
+    pub column: u32,
+}
+
+/// The file id of items without a location.
+pub(crate) const NO_SPAN: u32 = u32::MAX;
+
+const ENTRY_SIZE: usize = 16;
+
+use crate::index::Kind;
+use crate::parser;
+
+impl<'a> SourceSpanTable<'a> {
+    pub(crate) fn new(
+        num_sorts: u8,
+        num_terms: u32,
+        num_theorems: u32,
+        file: parser::Window<'a>,
+        files: &'a [u8],
+        spans: &'a [u8],
+    ) -> SourceSpanTable<'a> {
+        SourceSpanTable {
+            num_sorts,
+            num_terms,
+            num_theorems,
+            file,
+            files,
+            spans,
+        }
+    }
+
+    /// Returns the number of source files in the table.
+    pub fn num_files(&self) -> u32 {
+        (self.files.len() / 8) as u32
+    }
+
+    /// Returns the path of the source file with the given id, or `None` if the
+    /// id is out of range.
+    pub fn file_path(&self, id: u32) -> Option<&'a [u8]> {
+        let (_, path) = parser::seek_source_file(self.file, self.files, id as u64).ok()?;
+
+        Some(path)
+    }
+
+    /// Returns the location of the item with the given kind and index, or
+    /// `None` if the index is out of range or the item has no location.
+    pub fn get(&self, kind: Kind, idx: u64) -> Option<SourceSpan> {
+        let (from, len) = match kind {
+            Kind::Sort => (0, self.num_sorts as u64),
+            Kind::Term => (self.num_sorts as u64, self.num_terms as u64),
+            Kind::Theorem => (
+                self.num_sorts as u64 + self.num_terms as u64,
+                self.num_theorems as u64,
+            ),
+        };
+
+        if idx >= len {
+            return None;
+        }
+
+        let (_, span) = parser::seek_source_span(self.spans, from + idx).ok()?;
+
+        Some(span).filter(|span| span.file != NO_SPAN)
+    }
+
+    /// Returns an iterator over the kind, index and location of all items that
+    /// have a location.
+    pub fn iter(&self) -> SourceSpanIterator<'a> {
+        SourceSpanIterator {
+            spans: self.spans.chunks_exact(ENTRY_SIZE),
+            position: 0,
+            num_sorts: self.num_sorts as u64,
+            num_terms: self.num_terms as u64,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &SourceSpanTable<'a> {
+    type Item = (Kind, u64, SourceSpan);
+    type IntoIter = SourceSpanIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the entries of the source span table.
+pub struct SourceSpanIterator<'a> {
+    spans: core::slice::ChunksExact<'a, u8>,
+    position: u64,
+    num_sorts: u64,
+    num_terms: u64,
+}
+
+impl<'a> Iterator for SourceSpanIterator<'a> {
+    type Item = (Kind, u64, SourceSpan);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = self.spans.next()?;
+            let position = self.position;
+            self.position += 1;
+
+            let (_, span) = parser::parse_source_span(entry).ok()?;
+
+            if span.file == NO_SPAN {
+                continue;
+            }
+
+            let (kind, idx) = if position < self.num_sorts {
+                (Kind::Sort, position)
+            } else if position < self.num_sorts + self.num_terms {
+                (Kind::Term, position - self.num_sorts)
+            } else {
+                (Kind::Theorem, position - self.num_sorts - self.num_terms)
+            };
+
+            return Some((kind, idx, span));
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub use self::writer::SourceSpanWriter;
+
+#[cfg(feature = "alloc")]
+mod writer {
+    use alloc::collections::BTreeMap;
+    use alloc::vec::Vec;
+
+    use super::{SourceSpan, ENTRY_SIZE, NO_SPAN};
+    use crate::index::{self, Kind, WriteError, SOURCE_SPAN_TABLE_ID};
+    use crate::Mmb;
+
+    /// A builder for the source span table of a proof file.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct SourceSpanWriter {
+        files: Vec<Vec<u8>>,
+        spans: BTreeMap<(Kind, u64), SourceSpan>,
+    }
+
+    impl SourceSpanWriter {
+        /// Create a builder without any source files or locations.
+        pub fn new() -> SourceSpanWriter {
+            SourceSpanWriter::default()
+        }
+
+        /// Add a source file and return its id. Adding the same path again
+        /// returns the id it got the first time.
+        ///
+        /// The path is stored as a nul-terminated string, so paths containing
+        /// nul bytes are rejected with `WriteError::NulByte`.
+        pub fn add_file(&mut self, path: &[u8]) -> Result<u32, WriteError> {
+            if path.contains(&0) {
+                return Err(WriteError::NulByte);
+            }
+
+            match self.files.iter().position(|file| file == path) {
+                Some(id) => Ok(id as u32),
+                None => {
+                    self.files.push(path.to_vec());
+                    Ok((self.files.len() - 1) as u32)
+                }
+            }
+        }
+
+        /// Set the location of the item with the given kind and index.
+        ///
+        /// Locations are stored by item, so any index can be set without
+        /// allocating space for the items before it. Locations in files whose
+        /// ids were not returned by `add_file` are rejected with
+        /// `WriteError::UnknownFile`, so every written location refers to a
+        /// file of the table.
+        pub fn set(&mut self, kind: Kind, idx: u64, span: SourceSpan) -> Result<(), WriteError> {
+            if span.file == NO_SPAN || span.file as usize >= self.files.len() {
+                return Err(WriteError::UnknownFile(span.file));
+            }
+
+            self.spans.insert((kind, idx), span);
+
+            Ok(())
+        }
+
+        /// Build a copy of a proof file with this source span table added to
+        /// its index.
+        ///
+        /// The table and a new index are appended to the end of the file, so
+        /// all pointers into the existing file stay valid. The new index
+        /// contains the tables of the old index, except for an existing source
+        /// span table, which is replaced. Locations of items that do not exist
+        /// in the file are ignored.
+        pub fn write(&self, mmb: &Mmb) -> Vec<u8> {
+            let counts = [
+                (Kind::Sort, mmb.num_sorts() as u64),
+                (Kind::Term, mmb.num_terms() as u64),
+                (Kind::Theorem, mmb.num_theorems() as u64),
+            ];
+
+            index::append_table(mmb, SOURCE_SPAN_TABLE_ID, |ptr| {
+                let num_spans: u64 = counts.iter().map(|&(_, count)| count).sum();
+                let paths_ptr =
+                    ptr + 8 + 8 * self.files.len() as u64 + ENTRY_SIZE as u64 * num_spans;
+
+                let mut table = Vec::new();
+                table.extend_from_slice(&(self.files.len() as u32).to_le_bytes());
+                table.extend_from_slice(&0u32.to_le_bytes());
+
+                let mut path_ptr = paths_ptr;
+
+                for file in &self.files {
+                    table.extend_from_slice(&path_ptr.to_le_bytes());
+                    path_ptr += file.len() as u64 + 1;
+                }
+
+                for &(kind, count) in &counts {
+                    for idx in 0..count {
+                        let (file, line, column) = match self.spans.get(&(kind, idx)) {
+                            Some(span) => (span.file, span.line, span.column),
+                            None => (NO_SPAN, 0, 0),
+                        };
+
+                        table.extend_from_slice(&file.to_le_bytes());
+                        table.extend_from_slice(&line.to_le_bytes());
+                        table.extend_from_slice(&column.to_le_bytes());
+                        table.extend_from_slice(&0u32.to_le_bytes());
+                    }
+                }
+
+                for file in &self.files {
+                    table.extend_from_slice(file);
+                    table.push(0);
+                }
+
+                table
+            })
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::index::WriteError;
+    use crate::testing;
+    use crate::Mmb;
+
+    fn span(file: u32, line: u32) -> SourceSpan {
+        SourceSpan {
+            file,
+            line,
+            column: 1,
+        }
+    }
+
+    #[test]
+    fn written_spans_are_read_back() {
+        let file = testing::logic().build();
+        let mmb = Mmb::from(&file).unwrap();
+
+        let mut writer = SourceSpanWriter::new();
+        let a = writer.add_file(b"a.mm1").unwrap();
+        let b = writer.add_file(b"b.mm1").unwrap();
+        assert_eq!(writer.add_file(b"a.mm1"), Ok(a));
+
+        writer.set(Kind::Sort, 0, span(a, 1)).unwrap();
+        writer.set(Kind::Term, 1, span(a, 3)).unwrap();
+        writer.set(Kind::Theorem, 0, span(b, 7)).unwrap();
+        // Items that do not exist are ignored without allocating for them.
+        writer.set(Kind::Theorem, u64::MAX, span(b, 9)).unwrap();
+
+        let written = writer.write(&mmb);
+        let mmb = Mmb::from(&written).unwrap();
+        let index = mmb.index().unwrap();
+        let spans = index.source_spans().unwrap();
+
+        assert_eq!(spans.num_files(), 2);
+        assert_eq!(spans.file_path(a), Some(&b"a.mm1"[..]));
+        assert_eq!(spans.file_path(b), Some(&b"b.mm1"[..]));
+        assert_eq!(spans.file_path(2), None);
+
+        assert_eq!(spans.get(Kind::Sort, 0), Some(span(a, 1)));
+        assert_eq!(spans.get(Kind::Term, 0), None);
+        assert_eq!(spans.get(Kind::Term, 1), Some(span(a, 3)));
+        assert_eq!(spans.get(Kind::Theorem, 0), Some(span(b, 7)));
+        assert_eq!(spans.get(Kind::Theorem, 1), None);
+
+        assert_eq!(
+            spans.iter().collect::<Vec<_>>(),
+            [
+                (Kind::Sort, 0, span(a, 1)),
+                (Kind::Term, 1, span(a, 3)),
+                (Kind::Theorem, 0, span(b, 7)),
+            ]
+        );
+
+        // The name table of the original index is kept.
+        assert!(index.name_table().is_some());
+    }
+
+    #[test]
+    fn writing_again_replaces_the_table() {
+        let file = testing::logic().build();
+        let mmb = Mmb::from(&file).unwrap();
+
+        let mut writer = SourceSpanWriter::new();
+        let a = writer.add_file(b"a.mm1").unwrap();
+        writer.set(Kind::Term, 0, span(a, 1)).unwrap();
+        let first = writer.write(&mmb);
+
+        let mut writer = SourceSpanWriter::new();
+        let b = writer.add_file(b"b.mm1").unwrap();
+        writer.set(Kind::Term, 0, span(b, 2)).unwrap();
+        let second = writer.write(&Mmb::from(&first).unwrap());
+
+        let mmb = Mmb::from(&second).unwrap();
+        let index = mmb.index().unwrap();
+        let ids: Vec<_> = index.iter().map(|entry| entry.id()).collect();
+
+        assert_eq!(ids.len(), 2);
+        assert_eq!(
+            index.source_spans().unwrap().get(Kind::Term, 0),
+            Some(span(b, 2))
+        );
+    }
+
+    #[test]
+    fn paths_with_nul_bytes_are_rejected() {
+        let mut writer = SourceSpanWriter::new();
+
+        assert_eq!(writer.add_file(b"a\0b.mm1"), Err(WriteError::NulByte));
+        assert_eq!(writer.add_file(b"a.mm1"), Ok(0));
+    }
+
+    #[test]
+    fn spans_in_unknown_files_are_rejected() {
+        let mut writer = SourceSpanWriter::new();
+
+        assert_eq!(
+            writer.set(Kind::Term, 0, span(0, 1)),
+            Err(WriteError::UnknownFile(0))
+        );
+
+        let a = writer.add_file(b"a.mm1").unwrap();
+        assert_eq!(writer.set(Kind::Term, 0, span(a, 1)), Ok(()));
+        assert_eq!(
+            writer.set(Kind::Term, 1, span(a + 1, 1)),
+            Err(WriteError::UnknownFile(a + 1))
+        );
+        assert_eq!(
+            writer.set(Kind::Term, 1, span(NO_SPAN, 1)),
+            Err(WriteError::UnknownFile(NO_SPAN))
+        );
+
+        // Rejected locations are not written.
+        let file = testing::logic().build();
+        let written = writer.write(&Mmb::from(&file).unwrap());
+        let mmb = Mmb::from(&written).unwrap();
+        let spans = mmb.index().unwrap().source_spans().unwrap();
+
+        assert_eq!(
+            spans.iter().collect::<Vec<_>>(),
+            [(Kind::Term, 0, span(a, 1))]
+        );
+    }
+}
@@ -1,18 +1,67 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub use mmb_types::opcode;
+#[cfg(feature = "alloc")]
+pub mod cache;
+#[cfg(feature = "alloc")]
+pub mod check;
+pub mod command;
+#[cfg(feature = "alloc")]
+pub mod diff;
 mod error;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "alloc")]
+pub mod graph;
+#[cfg(feature = "alloc")]
+pub mod hash;
+mod header;
 pub mod index;
+#[cfg(feature = "alloc")]
+pub mod model;
+#[cfg(feature = "alloc")]
+mod owned;
 mod parser;
+#[cfg(feature = "std")]
+pub mod reader;
+#[cfg(feature = "serde")]
+mod serialize;
+pub mod statement;
+#[cfg(feature = "alloc")]
+pub mod stats;
+mod table;
+#[cfg(all(test, feature = "alloc"))]
+mod testing;
+pub mod version;
 pub mod visitor;
 
-pub use visitor::{ProofStream, UnifyStream, Visitor};
+#[cfg(feature = "std")]
+pub use file::MmbFile;
+pub use header::{Header, HEADER_SIZE};
+#[cfg(feature = "alloc")]
+pub use model::{CollectingVisitor, MmbModel};
+#[cfg(feature = "alloc")]
+pub use owned::OwnedMmb;
+#[cfg(feature = "std")]
+pub use reader::StreamReader;
+pub use statement::{DeclarationIterator, MalformedStatement, StatementEntry, StatementIterator};
+pub use table::{TermEntry, TheoremEntry};
+pub use version::{Compatibility, FormatError, Warning};
+pub use visitor::{
+    Discard, ProofStream, UnifyStream, VisitError, VisitOptions, VisitResult, Visitor,
+};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
 
 #[derive(Debug)]
 pub struct Mmb<'a> {
     file: &'a [u8],
-    version: u8,
-    num_sorts: u8,
-    num_terms: u32,
-    num_theorems: u32,
+    header: Header,
     sorts: &'a [u8],
     terms: &'a [u8],
     theorems: &'a [u8],
@@ -21,11 +70,25 @@ pub struct Mmb<'a> {
 }
 
 impl<'a> Mmb<'a> {
-    /// Build a `Mmb` struct by parsing the file header
+    /// Build a `Mmb` struct by parsing the file header, or return `None` if
+    /// the file has an unsupported version, or the header or the tables it
+    /// points to are malformed
     pub fn from(file: &'a [u8]) -> Option<Mmb<'a>> {
-        let file = parser::parse(file);
-        let x = file.unwrap();
-        Some(x.1)
+        Mmb::with_compatibility(file, Compatibility::Strict).ok()
+    }
+
+    /// Build a `Mmb` struct by parsing the file header, accepting the versions
+    /// allowed by the given compatibility policy
+    pub fn with_compatibility(
+        file: &'a [u8],
+        compatibility: Compatibility,
+    ) -> Result<Mmb<'a>, FormatError> {
+        let (_, header) = parser::parse_header(file).map_err(|_| FormatError::Malformed)?;
+        compatibility.check(header.version)?;
+
+        let (_, mmb) = parser::parse(file).map_err(|_| FormatError::Malformed)?;
+
+        Ok(mmb)
     }
 
     /// Return the slice containing the entire file
@@ -33,24 +96,40 @@ impl<'a> Mmb<'a> {
         self.file
     }
 
+    /// Return the file header
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
     /// Return the version of the proof file format
     pub fn version(&self) -> u8 {
-        self.version
+        self.header.version
+    }
+
+    /// Return an iterator over the parts of the file this crate does not
+    /// understand, such as a newer version or unknown index tables
+    pub fn warnings(&self) -> version::Warnings<'a> {
+        let version = self.header.version;
+
+        version::Warnings {
+            version: Some(version).filter(|&version| version > version::LATEST_VERSION),
+            entries: self.index.as_ref().map(|index| index.iter()),
+        }
     }
 
     /// Return the number of sorts in the sort table
     pub fn num_sorts(&self) -> u8 {
-        self.num_sorts
+        self.header.num_sorts
     }
 
     /// Return the number of terms in the term table
     pub fn num_terms(&self) -> u32 {
-        self.num_terms
+        self.header.num_terms
     }
 
     /// Return the number of theorems in the theorem table
     pub fn num_theorems(&self) -> u32 {
-        self.num_theorems
+        self.header.num_theorems
     }
 
     /// Return the slice containing the sort table
@@ -68,30 +147,176 @@ impl<'a> Mmb<'a> {
         self.theorems
     }
 
+    /// Return the entry of the term table with the given index, or `None` if
+    /// the index is out of range or the entry cannot be parsed
+    pub fn term(&self, idx: u32) -> Option<TermEntry<'a>> {
+        let (_, term) = parser::seek_term_entry(self.file, self.terms, idx).ok()?;
+
+        Some(term)
+    }
+
+    /// Return the entry of the theorem table with the given index, or `None`
+    /// if the index is out of range or the entry cannot be parsed
+    pub fn theorem(&self, idx: u32) -> Option<TheoremEntry<'a>> {
+        let (_, theorem) = parser::seek_theorem_entry(self.file, self.theorems, idx).ok()?;
+
+        Some(theorem)
+    }
+
     /// Return the slice containing the proof section
     pub fn proofs(&self) -> &[u8] {
         self.proofs
     }
 
+    /// Return the offset of the proof section from the start of the file
+    pub fn proofs_ptr(&self) -> usize {
+        self.header.proofs_ptr as usize
+    }
+
+    /// Return an iterator over the statements in the proof section
+    pub fn statements(&self) -> StatementIterator<'a> {
+        StatementIterator::new(self.proofs)
+    }
+
+    /// Return an iterator over the statements in the proof section, together
+    /// with the kind and index of the item declared by each statement
+    pub fn declarations(&self) -> DeclarationIterator<'a> {
+        DeclarationIterator::new(self.proofs)
+    }
+
     /// Return a reference to the optional index section
-    pub fn index(&self) -> Option<&index::Index> {
+    pub fn index(&self) -> Option<&index::Index<'a>> {
         self.index.as_ref()
     }
 
+    /// Return the axioms and unproven theorems the theorem with the given
+    /// index transitively depends on.
+    ///
+    /// This builds the entire dependency graph of the file, so use
+    /// `graph::DependencyGraph::axioms_used` when querying many theorems.
+    #[cfg(feature = "alloc")]
+    pub fn axioms_used(&self, theorem_idx: u32) -> graph::AxiomFootprint {
+        graph::DependencyGraph::new(self).axioms_used(theorem_idx)
+    }
+
+    /// Return the theorems without a proof stream and all theorems that
+    /// transitively depend on one, with the offsets of their statements.
+    ///
+    /// See `graph::DependencyGraph::unproven`.
+    #[cfg(feature = "alloc")]
+    pub fn unproven(&self) -> Vec<graph::Unproven> {
+        graph::DependencyGraph::new(self).unproven()
+    }
+
+    /// Visit the sorts, the statement stream, the terms and the theorems, in
+    /// that order.
+    ///
+    /// This is neither the order of the file, where the term and theorem
+    /// tables precede the proof section, nor the order of declaration. Use
+    /// `visit_in_declaration_order` for the latter.
+    ///
+    /// Returns `ControlFlow::Break` if the visitor stopped the traversal, and
+    /// an error if the file could not be parsed or the visitor returned one.
     pub fn visit<V: Visitor<'a>>(
         &self,
         visitor: &mut V,
-    ) -> Result<(), nom::Err<error::ParseError>> {
-        let (_, _) = parser::parse_sorts(self.sorts, self.num_sorts, visitor)?;
-        let (_, _) = parser::scan_statement_stream(self.proofs, visitor)?;
-        let (_, _) = parser::parse_terms(self.file, self.terms, self.num_terms as usize, visitor)?;
-        let (_, _) = parser::parse_theorems(
-            self.file,
-            self.theorems,
-            self.num_theorems as usize,
-            visitor,
-        )?;
-
-        Ok(())
+    ) -> Result<ControlFlow<()>, VisitError<'a, V::Error>> {
+        self.visit_with(visitor, VisitOptions::default())
+    }
+
+    /// Visit the parts of the file selected by `options`, in the same order as
+    /// `visit`, skipping the other parts entirely.
+    pub fn visit_with<V: Visitor<'a>>(
+        &self,
+        visitor: &mut V,
+        options: VisitOptions,
+    ) -> Result<ControlFlow<()>, VisitError<'a, V::Error>> {
+        if options.sorts {
+            let flow = self.visit_sorts(visitor)?;
+
+            if flow.is_break() {
+                return Ok(flow);
+            }
+        }
+
+        if options.statements {
+            let flow = parser::scan_statement_stream(self.proofs, options.proofs, visitor)?;
+
+            if flow.is_break() {
+                return Ok(flow);
+            }
+        }
+
+        if options.terms {
+            let flow = self.visit_terms(visitor)?;
+
+            if flow.is_break() {
+                return Ok(flow);
+            }
+        }
+
+        if options.theorems {
+            let flow = self.visit_theorems(visitor)?;
+
+            if flow.is_break() {
+                return Ok(flow);
+            }
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    /// Visit the entries of the sort table in order.
+    pub fn visit_sorts<V: Visitor<'a>>(
+        &self,
+        visitor: &mut V,
+    ) -> Result<ControlFlow<()>, VisitError<'a, V::Error>> {
+        parser::parse_sorts(self.sorts, self.header.num_sorts, visitor)
+    }
+
+    /// Visit the statements of the proof section in order, including their
+    /// proof streams.
+    ///
+    /// A statement that cannot be parsed before the end command of the
+    /// statement stream is an error, like in all other visit methods.
+    pub fn visit_statements<V: Visitor<'a>>(
+        &self,
+        visitor: &mut V,
+    ) -> Result<ControlFlow<()>, VisitError<'a, V::Error>> {
+        parser::scan_statement_stream(self.proofs, true, visitor)
+    }
+
+    /// Visit the entries of the term table in order.
+    pub fn visit_terms<V: Visitor<'a>>(
+        &self,
+        visitor: &mut V,
+    ) -> Result<ControlFlow<()>, VisitError<'a, V::Error>> {
+        let num_terms = self.header.num_terms as usize;
+
+        parser::parse_terms(self.file, self.terms, num_terms, visitor)
+    }
+
+    /// Visit the entries of the theorem table in order.
+    pub fn visit_theorems<V: Visitor<'a>>(
+        &self,
+        visitor: &mut V,
+    ) -> Result<ControlFlow<()>, VisitError<'a, V::Error>> {
+        let num_theorems = self.header.num_theorems as usize;
+
+        parser::parse_theorems(self.file, self.theorems, num_theorems, visitor)
+    }
+
+    /// Visit the statements of the proof section in order, and visit the table
+    /// entry of each sort, term and theorem right before the statement that
+    /// declares it.
+    ///
+    /// In a well-formed file, every item is thereby visited after all items it
+    /// may refer to. Table entries without a statement are visited after the
+    /// statement stream, sorts first, then terms, then theorems.
+    pub fn visit_in_declaration_order<V: Visitor<'a>>(
+        &self,
+        visitor: &mut V,
+    ) -> Result<ControlFlow<()>, VisitError<'a, V::Error>> {
+        parser::visit_declarations(self, true, visitor)
     }
 }
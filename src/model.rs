@@ -0,0 +1,384 @@
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::ops::ControlFlow;
+
+use crate::opcode::{self, Command, Proof, Unify};
+use crate::visitor::{ProofStream, UnifyStream, VisitError, VisitResult, Visitor};
+use crate::Mmb;
+
+/// A term of the term table.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Term {
+    /// The sort of the term, where the high bit is set for definitions.
+    pub sort: u8,
+    /// The range of the binders of the term in `MmbModel::binders`.
+    pub binders: (usize, usize),
+    /// The return type of the term.
+    pub ret_ty: u64,
+    /// The range of the unify stream of the definition in `MmbModel::unify`,
+    /// which is empty if the term is not a definition.
+    pub unify: (usize, usize),
+}
+
+/// A theorem of the theorem table.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Theorem {
+    /// The range of the binders of the theorem in `MmbModel::binders`.
+    pub binders: (usize, usize),
+    /// The range of the unify stream of the theorem in `MmbModel::unify`.
+    pub unify: (usize, usize),
+}
+
+/// A statement of the proof section.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statement {
+    /// The kind of the statement.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::StatementDef"))]
+    pub kind: opcode::Statement,
+    /// The offset of the statement relative to the start of the proof section.
+    pub offset: usize,
+    /// The range of the proof stream of the statement in `MmbModel::proof`, or
+    /// `None` if the statement has no proof stream.
+    pub proof: Option<(usize, usize)>,
+}
+
+/// An owned model of an entire proof file.
+///
+/// Binders and the commands of all unify and proof streams are stored in flat
+/// arrays, which the items refer to by `(start, end)` index ranges.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MmbModel {
+    /// The sort modifiers, indexed by sort.
+    pub sorts: Vec<u8>,
+    /// The terms, indexed by term.
+    pub terms: Vec<Term>,
+    /// The theorems, indexed by theorem.
+    pub theorems: Vec<Theorem>,
+    /// The statements of the proof section in order.
+    pub statements: Vec<Statement>,
+    /// The binders of all terms and theorems.
+    pub binders: Vec<u64>,
+    /// The commands of all unify streams, each ending with `Unify::End`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::unify_commands"))]
+    pub unify: Vec<Command<Unify>>,
+    /// The commands of all proof streams, each ending with `Proof::End`.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::proof_commands"))]
+    pub proof: Vec<Command<Proof>>,
+}
+
+impl MmbModel {
+    /// Build the model of a proof file by visiting it with a
+    /// `CollectingVisitor`.
+    pub fn new<'a>(mmb: &Mmb<'a>) -> Result<MmbModel, VisitError<'a, Infallible>> {
+        let mut visitor = CollectingVisitor::new();
+        // The visitor never breaks, so the traversal always runs to the end.
+        let _ = mmb.visit(&mut visitor)?;
+
+        Ok(visitor.into_model())
+    }
+
+    /// Returns the binders of the given range.
+    pub fn binders(&self, range: (usize, usize)) -> &[u64] {
+        self.binders.get(range.0..range.1).unwrap_or(&[])
+    }
+
+    /// Returns the unify commands of the given range.
+    pub fn unify(&self, range: (usize, usize)) -> &[Command<Unify>] {
+        self.unify.get(range.0..range.1).unwrap_or(&[])
+    }
+
+    /// Returns the proof commands of the given range.
+    pub fn proof(&self, range: (usize, usize)) -> &[Command<Proof>] {
+        self.proof.get(range.0..range.1).unwrap_or(&[])
+    }
+}
+
+/// A visitor that collects everything it visits into an `MmbModel`.
+///
+/// The visitor is its own unify and proof stream, and serves as a reference
+/// implementation of the contract of the `Visitor`, `UnifyStream` and
+/// `ProofStream` traits.
+#[derive(Debug, Clone, Default)]
+pub struct CollectingVisitor {
+    model: MmbModel,
+    unify_start: usize,
+    proof_start: usize,
+}
+
+impl CollectingVisitor {
+    /// Create a visitor with an empty model.
+    pub fn new() -> CollectingVisitor {
+        CollectingVisitor::default()
+    }
+
+    /// Returns the model collected so far.
+    pub fn model(&self) -> &MmbModel {
+        &self.model
+    }
+
+    /// Returns the collected model.
+    pub fn into_model(self) -> MmbModel {
+        self.model
+    }
+}
+
+impl UnifyStream for CollectingVisitor {
+    fn start(&mut self) {
+        self.unify_start = self.model.unify.len();
+    }
+
+    fn push(&mut self, command: Command<Unify>) {
+        self.model.unify.push(command);
+    }
+
+    fn done(&self) -> (usize, usize) {
+        (self.unify_start, self.model.unify.len())
+    }
+}
+
+impl ProofStream for CollectingVisitor {
+    fn start(&mut self) {
+        self.proof_start = self.model.proof.len();
+    }
+
+    fn push(&mut self, command: Command<Proof>) {
+        self.model.proof.push(command);
+    }
+
+    fn done(&self) -> (usize, usize) {
+        (self.proof_start, self.model.proof.len())
+    }
+}
+
+impl<'a> Visitor<'a> for CollectingVisitor {
+    type Binder = u64;
+    type Sort = u8;
+    type Statement = opcode::Statement;
+    type Unify = CollectingVisitor;
+    type Proof = CollectingVisitor;
+    type Error = Infallible;
+
+    fn parse_sort(&mut self, sort: u8) -> VisitResult<Infallible> {
+        self.model.sorts.push(sort);
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn parse_statement(
+        &mut self,
+        kind: opcode::Statement,
+        offset: usize,
+        _slice: &'a [u8],
+        proof: Option<(usize, usize)>,
+    ) -> VisitResult<Infallible> {
+        self.model.statements.push(Statement {
+            kind,
+            offset,
+            proof,
+        });
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn try_reserve_binder_slice(&mut self, nr: usize) -> Option<(&mut [u64], usize)> {
+        let binders = &mut self.model.binders;
+        let offset = binders.len();
+
+        binders.try_reserve(nr).ok()?;
+        binders.resize(offset + nr, 0);
+
+        Some((&mut binders[offset..], offset))
+    }
+
+    fn start_unify_stream(&mut self) -> Option<&mut CollectingVisitor> {
+        Some(self)
+    }
+
+    fn start_proof_stream(&mut self) -> Option<&mut CollectingVisitor> {
+        Some(self)
+    }
+
+    fn parse_term(
+        &mut self,
+        sort: u8,
+        binders: (usize, usize),
+        ret_ty: u64,
+        _unify: &'a [u8],
+        unify: (usize, usize),
+    ) -> VisitResult<Infallible> {
+        self.model.terms.push(Term {
+            sort,
+            binders,
+            ret_ty,
+            unify,
+        });
+
+        Ok(ControlFlow::Continue(()))
+    }
+
+    fn parse_theorem(
+        &mut self,
+        binders: (usize, usize),
+        _unify: &'a [u8],
+        unify: (usize, usize),
+    ) -> VisitResult<Infallible> {
+        self.model.theorems.push(Theorem { binders, unify });
+
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::testing;
+
+    fn unify(opcode: Unify, operand: u32) -> Command<Unify> {
+        Command { opcode, operand }
+    }
+
+    fn proof(opcode: Proof, operand: u32) -> Command<Proof> {
+        Command { opcode, operand }
+    }
+
+    #[test]
+    fn collected_model() {
+        let mut builder = testing::logic();
+        let not = 1;
+
+        builder.def(
+            "nn",
+            0,
+            &[0],
+            testing::unify(&[(Unify::Term, not), (Unify::Term, not), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Term, not), (Proof::Term, not)]),
+        );
+        builder.theorem(
+            "thm",
+            &[1],
+            testing::unify(&[(Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Thm, 0)]),
+        );
+
+        let file = builder.build();
+        let model = MmbModel::new(&Mmb::from(&file).unwrap()).unwrap();
+
+        assert_eq!(model.sorts, [0]);
+        assert_eq!(
+            model.statements,
+            [
+                Statement {
+                    kind: opcode::Statement::Sort,
+                    offset: 0,
+                    proof: None,
+                },
+                Statement {
+                    kind: opcode::Statement::TermDef,
+                    offset: 5,
+                    proof: None,
+                },
+                Statement {
+                    kind: opcode::Statement::TermDef,
+                    offset: 10,
+                    proof: None,
+                },
+                Statement {
+                    kind: opcode::Statement::Axiom,
+                    offset: 15,
+                    proof: None,
+                },
+                Statement {
+                    kind: opcode::Statement::TermDef,
+                    offset: 20,
+                    proof: Some((0, 4)),
+                },
+                Statement {
+                    kind: opcode::Statement::Thm,
+                    offset: 31,
+                    proof: Some((4, 7)),
+                },
+            ]
+        );
+        assert_eq!(
+            model.terms,
+            [
+                Term {
+                    sort: 0,
+                    binders: (0, 2),
+                    ret_ty: 0,
+                    unify: (0, 0),
+                },
+                Term {
+                    sort: 0,
+                    binders: (2, 3),
+                    ret_ty: 0,
+                    unify: (0, 0),
+                },
+                Term {
+                    sort: 0x80,
+                    binders: (3, 4),
+                    ret_ty: 0,
+                    unify: (0, 4),
+                },
+            ]
+        );
+        assert_eq!(
+            model.theorems,
+            [
+                Theorem {
+                    binders: (4, 5),
+                    unify: (4, 6),
+                },
+                Theorem {
+                    binders: (5, 6),
+                    unify: (6, 8),
+                },
+            ]
+        );
+        assert_eq!(model.binders, [0, 0, 0, 0, 0, 1]);
+        assert_eq!(model.binders(model.theorems[1].binders), [1]);
+
+        // Every stream ends with its end command.
+        assert_eq!(
+            model.unify(model.terms[2].unify),
+            [
+                unify(Unify::Term, not),
+                unify(Unify::Term, not),
+                unify(Unify::Ref, 0),
+                unify(Unify::End, 0),
+            ]
+        );
+        assert_eq!(
+            model.unify,
+            vec![
+                unify(Unify::Term, not),
+                unify(Unify::Term, not),
+                unify(Unify::Ref, 0),
+                unify(Unify::End, 0),
+                unify(Unify::Ref, 0),
+                unify(Unify::End, 0),
+                unify(Unify::Ref, 0),
+                unify(Unify::End, 0),
+            ]
+        );
+        assert_eq!(
+            model.proof,
+            vec![
+                proof(Proof::Ref, 0),
+                proof(Proof::Term, not),
+                proof(Proof::Term, not),
+                proof(Proof::End, 0),
+                proof(Proof::Ref, 0),
+                proof(Proof::Thm, 0),
+                proof(Proof::End, 0),
+            ]
+        );
+        assert_eq!(model.proof((4, 7))[1], proof(Proof::Thm, 0));
+    }
+}
@@ -0,0 +1,114 @@
+use alloc::sync::Arc;
+
+use crate::index::NameTable;
+use crate::version::{Compatibility, FormatError};
+use crate::Mmb;
+
+/// A proof file that owns its bytes.
+///
+/// Unlike `Mmb`, an `OwnedMmb` does not borrow its input, so it can be stored
+/// in long-lived structures and sent across threads. Cloning an `OwnedMmb` is
+/// cheap, as the bytes are shared. The borrowed views are created on demand by
+/// parsing the file header again, with the compatibility policy the file was
+/// accepted with.
+#[derive(Debug, Clone)]
+pub struct OwnedMmb {
+    file: Arc<[u8]>,
+    compatibility: Compatibility,
+}
+
+impl OwnedMmb {
+    /// Build an `OwnedMmb` from the bytes of a proof file, or return `None` if
+    /// the file has an unsupported version or the file header cannot be parsed.
+    pub fn new<T: Into<Arc<[u8]>>>(file: T) -> Option<OwnedMmb> {
+        OwnedMmb::with_compatibility(file, Compatibility::Strict).ok()
+    }
+
+    /// Build an `OwnedMmb` from the bytes of a proof file, accepting the
+    /// versions allowed by the given compatibility policy.
+    pub fn with_compatibility<T: Into<Arc<[u8]>>>(
+        file: T,
+        compatibility: Compatibility,
+    ) -> Result<OwnedMmb, FormatError> {
+        let file = file.into();
+
+        Mmb::with_compatibility(&file, compatibility)?;
+
+        Ok(OwnedMmb {
+            file,
+            compatibility,
+        })
+    }
+
+    /// Return the bytes of the entire file
+    pub fn file(&self) -> &Arc<[u8]> {
+        &self.file
+    }
+
+    /// Return a `Mmb` struct borrowing from this file
+    pub fn mmb(&self) -> Mmb<'_> {
+        Mmb::with_compatibility(&self.file, self.compatibility)
+            .expect("file header was parsed on construction")
+    }
+
+    /// Return the compatibility policy the file was accepted with
+    pub fn compatibility(&self) -> Compatibility {
+        self.compatibility
+    }
+
+    /// Return the name table of the index, or `None` if the file has no index
+    /// or the index does not contain a name table
+    pub fn name_table(&self) -> Option<NameTable<'_>> {
+        self.mmb().index()?.name_table()
+    }
+}
+
+impl AsRef<[u8]> for OwnedMmb {
+    fn as_ref(&self) -> &[u8] {
+        &self.file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::testing;
+
+    fn file(version: u8) -> Vec<u8> {
+        let mut file = testing::logic().build();
+        file[4] = version;
+
+        file
+    }
+
+    #[test]
+    fn supported_versions() {
+        let owned = OwnedMmb::new(file(1)).unwrap();
+
+        assert_eq!(owned.compatibility(), Compatibility::Strict);
+        assert_eq!(owned.mmb().num_sorts(), 1);
+        assert!(owned.name_table().is_some());
+        assert!(OwnedMmb::new(file(2)).is_none());
+    }
+
+    #[test]
+    fn newer_versions_are_accepted_on_request() {
+        assert_eq!(
+            OwnedMmb::with_compatibility(file(2), Compatibility::Strict).err(),
+            Some(FormatError::UnsupportedVersion(2))
+        );
+
+        let owned = OwnedMmb::with_compatibility(file(2), Compatibility::AcceptNewer).unwrap();
+
+        assert_eq!(owned.compatibility(), Compatibility::AcceptNewer);
+        assert_eq!(owned.mmb().version(), 2);
+        assert!(owned.name_table().is_some());
+
+        // Clones share the bytes and keep the compatibility policy.
+        let clone = owned.clone();
+        assert!(Arc::ptr_eq(clone.file(), owned.file()));
+        assert_eq!(clone.mmb().version(), 2);
+    }
+}
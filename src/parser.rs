@@ -1,17 +1,88 @@
+use core::convert::TryFrom;
+use core::ops::ControlFlow;
+
 use index::Entry;
 use nom::bytes::complete;
+use nom::error::ErrorKind;
 use nom::number;
 use nom::Err;
 
+use crate::header::{Header, HEADER_SIZE};
 use crate::index;
-use crate::opcode::{Command, Proof, Unify};
-use crate::visitor::{ProofStream, UnifyStream, Visitor};
+use crate::index::proof_map::{self, ProofStep};
+use crate::index::source_spans::SourceSpan;
+use crate::opcode::{Command, Proof, Statement, Unify};
+use crate::statement::{declared_kind, StatementEntry};
+use crate::table::{TermEntry, TheoremEntry};
+use crate::visitor::{Discard, ProofStream, UnifyStream, VisitError, Visitor};
 use crate::Mmb;
-use crate::{error::*, index::name_table::Name};
+use crate::{error::*, index::name_table::Name, index::Kind};
 
 const TABLE_ENTRY_SIZE: u64 = 8 * 2;
 
-pub fn parse(input: &[u8]) -> IResult<Mmb> {
+/// The result of visiting a part of a proof file.
+pub type Visited<'a, T, E> = Result<T, VisitError<'a, E>>;
+
+/// Return early if the visitor asked to stop the traversal.
+macro_rules! try_visit {
+    ($e:expr) => {
+        if let ControlFlow::Break(()) = $e? {
+            return Ok(ControlFlow::Break(()));
+        }
+    };
+}
+
+/// Take `len` bytes, failing instead of truncating if `len` does not fit into
+/// `usize`.
+fn take_bytes(input: &[u8], len: u64) -> IResult<'_, &[u8]> {
+    let len = usize::try_from(len)
+        .map_err(|_| Err::Error(ParseError(input, ErrorType::Nom(ErrorKind::TooLarge))))?;
+
+    complete::take(len)(input)
+}
+
+/// A part of a proof file that starts at `base` in the file, against which the
+/// file relative pointers of the index are resolved.
+///
+/// For proof files in memory, this is the entire file. Pointers in front of
+/// the part are treated like pointers past the end of the file.
+#[derive(Debug, Copy, Clone)]
+pub struct Window<'a> {
+    data: &'a [u8],
+    base: u64,
+}
+
+impl<'a> Window<'a> {
+    pub fn new(data: &'a [u8], base: u64) -> Window<'a> {
+        Window { data, base }
+    }
+
+    /// Skip to the given pointer, leaving the rest of the file as the
+    /// remaining input.
+    pub fn seek(&self, ptr: u64) -> IResult<'a, ()> {
+        let offset = ptr.checked_sub(self.base).ok_or(Err::Error(ParseError(
+            self.data,
+            ErrorType::Nom(ErrorKind::Eof),
+        )))?;
+
+        let (left, _) = take_bytes(self.data, offset)?;
+
+        Ok((left, ()))
+    }
+}
+
+/// Take `num` entries of `size` bytes each, failing instead of overflowing if
+/// the total size does not fit into `usize`.
+fn take_entries(input: &[u8], num: u64, size: u64) -> IResult<'_, &[u8]> {
+    let len = num.checked_mul(size).ok_or(Err::Error(ParseError(
+        input,
+        ErrorType::Nom(ErrorKind::TooLarge),
+    )))?;
+
+    take_bytes(input, len)
+}
+
+pub fn parse_header(input: &[u8]) -> IResult<'_, Header> {
     let (i, _) = take_magic(input)?;
 
     let (i, version) = number::complete::le_u8(i)?;
@@ -26,22 +97,49 @@ pub fn parse(input: &[u8]) -> IResult<Mmb> {
     let (i, _padding) = complete::take(4u8)(i)?;
 
     let (i, index_ptr) = number::complete::le_u64(i)?;
-    let (i, sorts) = complete::take(num_sorts)(i)?;
 
-    let index = if index_ptr != 0 {
-        let (index, _) = complete::take(index_ptr as usize)(input)?;
+    let header = Header {
+        version,
+        num_sorts,
+        num_terms,
+        num_theorems,
+        terms_ptr,
+        theorems_ptr,
+        proofs_ptr,
+        index_ptr,
+    };
 
-        let (j, num) = number::complete::le_u64(index)?;
-        let (_, entries) = complete::take(num * TABLE_ENTRY_SIZE)(j)?;
+    Ok((i, header))
+}
 
-        let index = index::Index {
-            file: input,
-            num_sorts,
-            num_terms,
-            num_theorems,
-            num_entries: num,
-            entries,
-        };
+pub fn parse_tables<'a>(
+    file: &'a [u8],
+    header: &Header,
+) -> IResult<'a, (&'a [u8], &'a [u8], &'a [u8])> {
+    let (i, _) = complete::take(HEADER_SIZE)(file)?;
+    let (i, sorts) = complete::take(header.num_sorts)(i)?;
+
+    let (terms, _) = complete::take(header.terms_ptr as usize)(file)?;
+    let (_, terms) = take_entries(terms, header.num_terms as u64, 8)?;
+
+    let (theorems, _) = complete::take(header.theorems_ptr as usize)(file)?;
+    let (_, theorems) = take_entries(theorems, header.num_theorems as u64, 8)?;
+
+    Ok((i, (sorts, terms, theorems)))
+}
+
+pub fn parse(input: &[u8]) -> IResult<'_, Mmb<'_>> {
+    let (_, header) = parse_header(input)?;
+    let (i, (sorts, terms, theorems)) = parse_tables(input, &header)?;
+
+    let Header {
+        proofs_ptr,
+        index_ptr,
+        ..
+    } = header;
+
+    let index = if index_ptr != 0 {
+        let (_, index) = parse_index(Window::new(input, 0), &header)?;
         /*
         for _ in 0..num {
             let (k, id) = number::complete::le_u32(j)?;
@@ -81,20 +179,11 @@ pub fn parse(input: &[u8]) -> IResult<Mmb> {
 
     let (proofs, _) = complete::take(proofs_ptr as usize)(input)?;
 
-    let (terms, _) = complete::take(terms_ptr as usize)(input)?;
-    let (_, terms) = complete::take(num_terms as usize * 8)(terms)?;
-
-    let (theorems, _) = complete::take(theorems_ptr as usize)(input)?;
-    let (_, theorems) = complete::take(num_theorems as usize * 8)(theorems)?;
-
     Ok((
         i,
         Mmb {
             file: input,
-            version,
-            num_sorts,
-            num_terms,
-            num_theorems,
+            header,
             sorts,
             terms,
             theorems,
@@ -104,6 +193,25 @@ pub fn parse(input: &[u8]) -> IResult<Mmb> {
     ))
 }
 
+/// Parse the index at the index pointer of the header.
+pub fn parse_index<'a>(file: Window<'a>, header: &Header) -> IResult<'a, index::Index<'a>> {
+    let (index, _) = file.seek(header.index_ptr)?;
+
+    let (left, num) = number::complete::le_u64(index)?;
+    let (left, entries) = take_entries(left, num, TABLE_ENTRY_SIZE)?;
+
+    let index = index::Index {
+        file,
+        num_sorts: header.num_sorts,
+        num_terms: header.num_terms,
+        num_theorems: header.num_theorems,
+        num_entries: num,
+        entries,
+    };
+
+    Ok((left, index))
+}
+
 pub fn parse_index_entry<'a>(entries: &'a [u8]) -> IResult<'a, Entry> {
     let (left, id) = number::complete::le_u32(entries)?;
     // TODO: check if padding is zero?
@@ -117,26 +225,39 @@ pub fn parse_index_entry<'a>(entries: &'a [u8]) -> IResult<'a, Entry> {
 
 const NAME_ENTRY_SIZE: u64 = 8 * 2;
 
-pub fn parse_name_entries<'a>(file: &'a [u8], num: u64, ptr: u64) -> IResult<'a, &'a [u8]> {
-    let num = num * NAME_ENTRY_SIZE;
-    let (entries, _) = complete::take(ptr as usize)(file)?;
-    let (left, entries) = complete::take(num)(entries)?;
+pub fn parse_name_entries<'a>(file: Window<'a>, num: u64, ptr: u64) -> IResult<'a, &'a [u8]> {
+    let (entries, _) = file.seek(ptr)?;
+    let (left, entries) = take_entries(entries, num, NAME_ENTRY_SIZE)?;
 
     Ok((left, entries))
 }
 
-pub fn seek_name_entry<'a>(file: &'a [u8], entries: &'a [u8], idx: u64) -> IResult<'a, Name<'a>> {
-    let (entry, _) = complete::take(idx * NAME_ENTRY_SIZE)(entries)?;
+pub fn seek_name_entry<'a>(file: Window<'a>, entries: &'a [u8], idx: u64) -> IResult<'a, Name<'a>> {
+    let (entry, _) = take_entries(entries, idx, NAME_ENTRY_SIZE)?;
     let (left, name) = parse_name_entry(file, entry)?;
 
     Ok((left, name))
 }
 
-pub fn parse_name_entry<'a>(file: &'a [u8], entry: &'a [u8]) -> IResult<'a, Name<'a>> {
+#[cfg(feature = "alloc")]
+pub fn seek_raw_name_entry(entries: &[u8], idx: u64) -> IResult<'_, (u64, u64)> {
+    let (entry, _) = take_entries(entries, idx, NAME_ENTRY_SIZE)?;
+    let (left, raw) = parse_raw_name_entry(entry)?;
+
+    Ok((left, raw))
+}
+
+pub fn parse_raw_name_entry(entry: &[u8]) -> IResult<'_, (u64, u64)> {
     let (left, ptr) = number::complete::le_u64(entry)?;
     let (left, name_ptr) = number::complete::le_u64(left)?;
 
-    let (name, _) = complete::take(name_ptr as usize)(file)?;
+    Ok((left, (ptr, name_ptr)))
+}
+
+pub fn parse_name_entry<'a>(file: Window<'a>, entry: &'a [u8]) -> IResult<'a, Name<'a>> {
+    let (left, (ptr, name_ptr)) = parse_raw_name_entry(entry)?;
+
+    let (name, _) = file.seek(name_ptr)?;
     let (_, name) = parse_nul_terminated_slice(name)?;
 
     let name = Name { ptr, name };
@@ -144,16 +265,121 @@ pub fn parse_name_entry<'a>(file: &'a [u8], entry: &'a [u8]) -> IResult<'a, Name
     Ok((left, name))
 }
 
-pub fn subslice_name_table<'a>(entries: &'a [u8], from: u64, len: u64) -> IResult<'a, &'a [u8]> {
-    let from = from * NAME_ENTRY_SIZE;
-    let len = len * NAME_ENTRY_SIZE;
+/// Parse the sorted name order, failing if it does not contain exactly `num`
+/// entries.
+pub fn parse_sorted_names<'a>(file: Window<'a>, num: u64, ptr: u64) -> IResult<'a, &'a [u8]> {
+    let (table, _) = file.seek(ptr)?;
+    let (left, len) = number::complete::le_u64(table)?;
+
+    if len != num {
+        return Err(Err::Error(ParseError(
+            table,
+            ErrorType::Nom(ErrorKind::Verify),
+        )));
+    }
+
+    take_entries(left, num, 8)
+}
+
+pub fn seek_sorted_name(order: &[u8], idx: u64) -> IResult<'_, u64> {
+    let (entry, _) = take_entries(order, idx, 8)?;
 
-    let (left, _) = complete::take(from)(entries)?;
-    let (left, subslice) = complete::take(len)(left)?;
+    number::complete::le_u64(entry)
+}
+
+pub fn subslice_name_table<'a>(entries: &'a [u8], from: u64, len: u64) -> IResult<'a, &'a [u8]> {
+    let (left, _) = take_entries(entries, from, NAME_ENTRY_SIZE)?;
+    let (left, subslice) = take_entries(left, len, NAME_ENTRY_SIZE)?;
 
     Ok((left, subslice))
 }
 
+const SOURCE_SPAN_SIZE: u64 = 16;
+
+pub fn parse_source_span_table<'a>(
+    file: Window<'a>,
+    num: u64,
+    ptr: u64,
+) -> IResult<'a, (&'a [u8], &'a [u8])> {
+    let (table, _) = file.seek(ptr)?;
+    let (left, num_files) = number::complete::le_u32(table)?;
+    let (left, _padding) = number::complete::le_u32(left)?;
+    let (left, files) = take_entries(left, num_files as u64, 8)?;
+    let (left, spans) = take_entries(left, num, SOURCE_SPAN_SIZE)?;
+
+    Ok((left, (files, spans)))
+}
+
+pub fn seek_source_span(spans: &[u8], idx: u64) -> IResult<'_, SourceSpan> {
+    let (entry, _) = take_entries(spans, idx, SOURCE_SPAN_SIZE)?;
+
+    parse_source_span(entry)
+}
+
+pub fn parse_source_span(entry: &[u8]) -> IResult<'_, SourceSpan> {
+    let (left, file) = number::complete::le_u32(entry)?;
+    let (left, line) = number::complete::le_u32(left)?;
+    let (left, column) = number::complete::le_u32(left)?;
+    let (left, _padding) = number::complete::le_u32(left)?;
+
+    let span = SourceSpan { file, line, column };
+
+    Ok((left, span))
+}
+
+pub fn seek_source_file<'a>(file: Window<'a>, files: &'a [u8], idx: u64) -> IResult<'a, &'a [u8]> {
+    let (entry, _) = take_entries(files, idx, 8)?;
+    let (left, ptr) = number::complete::le_u64(entry)?;
+
+    let (path, _) = file.seek(ptr)?;
+    let (_, path) = parse_nul_terminated_slice(path)?;
+
+    Ok((left, path))
+}
+
+pub fn parse_proof_source_map<'a>(file: Window<'a>, ptr: u64) -> IResult<'a, &'a [u8]> {
+    let (table, _) = file.seek(ptr)?;
+    let (left, num) = number::complete::le_u64(table)?;
+    let (left, entries) = take_entries(left, num, proof_map::ENTRY_SIZE)?;
+
+    Ok((left, entries))
+}
+
+pub fn seek_proof_step_offset(entries: &[u8], idx: u64) -> IResult<'_, u64> {
+    let (entry, _) = take_entries(entries, idx, proof_map::ENTRY_SIZE)?;
+
+    number::complete::le_u64(entry)
+}
+
+pub fn seek_proof_step<'a>(
+    file: Window<'a>,
+    entries: &'a [u8],
+    idx: u64,
+) -> IResult<'a, ProofStep<'a>> {
+    let (entry, _) = take_entries(entries, idx, proof_map::ENTRY_SIZE)?;
+
+    parse_proof_step(file, entry)
+}
+
+pub fn parse_proof_step<'a>(file: Window<'a>, entry: &'a [u8]) -> IResult<'a, ProofStep<'a>> {
+    let (left, offset) = number::complete::le_u64(entry)?;
+    let (left, span) = parse_source_span(left)?;
+    let (left, name_ptr) = number::complete::le_u64(left)?;
+
+    let name = if name_ptr != 0 {
+        let (name, _) = file.seek(name_ptr)?;
+        let (_, name) = parse_nul_terminated_slice(name)?;
+
+        Some(name)
+    } else {
+        None
+    };
+
+    let step = ProofStep { offset, span, name };
+
+    Ok((left, step))
+}
+
 fn parse_binders<'a, T: From<u64>>(input: &'a [u8], slice: &mut [T]) -> IResult<'a, ()> {
     let mut left = input;
 
@@ -173,21 +399,53 @@ fn parse_nul_terminated_slice(i: &[u8]) -> IResult<'_, &[u8]> {
     complete::take(len)(i)
 }
 
-fn parse_term<'a, V: Visitor<'a>>(
-    file: &'a [u8],
-    input: &'a [u8],
-    visitor: &mut V,
-) -> IResult<'a, ()> {
+pub fn parse_term_header(input: &[u8]) -> IResult<'_, (u16, u8, u32)> {
     let (i, num_args) = number::complete::le_u16(input)?;
     let (i, sort) = number::complete::le_u8(i)?;
     let (i, _padding) = complete::take(1usize)(i)?;
     let (i, ptr_binders) = number::complete::le_u32(i)?;
 
+    Ok((i, (num_args, sort, ptr_binders)))
+}
+
+pub fn seek_term_entry<'a>(
+    file: &'a [u8],
+    terms: &'a [u8],
+    idx: u32,
+) -> IResult<'a, TermEntry<'a>> {
+    let (entry, _) = take_entries(terms, idx as u64, 8)?;
+    let (left, (num_args, sort, ptr_binders)) = parse_term_header(entry)?;
+
+    let (binders, _) = complete::take(ptr_binders as usize)(file)?;
+    let (ret_ty, binders) = complete::take(num_args as usize * 8)(binders)?;
+    let (unify, ret_ty) = number::complete::le_u64(ret_ty)?;
+
+    let unify = if (sort & 0x80) == 0x80 { unify } else { &[] };
+
+    let term = TermEntry {
+        sort,
+        binders,
+        ret_ty,
+        unify,
+    };
+
+    Ok((left, term))
+}
+
+fn parse_term<'a, V: Visitor<'a>>(
+    file: &'a [u8],
+    input: &'a [u8],
+    visitor: &mut V,
+) -> Visited<'a, ControlFlow<()>, V::Error> {
+    let (_, (num_args, sort, ptr_binders)) = parse_term_header(input)?;
+
     let (binders, _) = complete::take(ptr_binders as usize)(file)?;
     let (ret_ty, binders) = complete::take(num_args as usize * 8)(binders)?;
     let (opt_unify, ret_ty) = complete::take(8usize)(ret_ty)?;
 
-    let (offset, ret_ty) = {
+    // The visitor may reserve fewer binders than requested, such as none to
+    // discard them, and the range only covers the binders it stored.
+    let (range, ret_ty) = {
         let (slice, offset) = visitor
             .try_reserve_binder_slice(num_args as usize)
             .ok_or(Err::Error(ParseError(input, ErrorType::Memory)))?;
@@ -196,30 +454,27 @@ fn parse_term<'a, V: Visitor<'a>>(
 
         let (_, ret_ty) = number::complete::le_u64(ret_ty)?;
 
-        (offset, ret_ty)
+        ((offset, offset + slice.len()), ret_ty)
     };
 
     let stream = visitor.start_unify_stream();
 
-    let unify = if (sort & 0x80) == 0x80 {
+    let (unify, unify_indices) = if (sort & 0x80) == 0x80 {
         // is definition
-        let (_, unify) = take_unify_until_end(opt_unify, stream)?;
+        let (_, unify) = decode_unify_stream(opt_unify, stream)?;
         unify
     } else {
-        (Default::default(), 0usize)
-    };
-
-    let unify_indices = stream.done();
+        let indices = stream.map_or((0, 0), |stream| {
+            stream.start();
+            stream.done()
+        });
 
-    visitor.parse_term(
-        sort,
-        (offset, offset + num_args as usize),
-        From::from(ret_ty),
-        unify.0,
-        unify_indices,
-    );
+        (Default::default(), indices)
+    };
 
-    Ok((i, ()))
+    visitor
+        .parse_term(sort, range, From::from(ret_ty), unify, unify_indices)
+        .map_err(VisitError::Visitor)
 }
 
 pub fn parse_terms<'a, V: Visitor<'a>>(
@@ -227,77 +482,77 @@ pub fn parse_terms<'a, V: Visitor<'a>>(
     input: &'a [u8],
     num_terms: usize,
     visitor: &mut V,
-) -> IResult<'a, ()> {
-    let mut parse_term = |i| parse_term(file, i, visitor);
+) -> Visited<'a, ControlFlow<()>, V::Error> {
+    for entry in input.chunks_exact(8).take(num_terms) {
+        try_visit!(parse_term(file, entry, visitor));
+    }
 
-    let mut left = input;
+    Ok(ControlFlow::Continue(()))
+}
 
-    for _ in 0..num_terms {
-        let (i, _) = parse_term(left)?;
+pub fn parse_theorem_header(input: &[u8]) -> IResult<'_, (u16, u32)> {
+    let (i, num_args) = number::complete::le_u16(input)?;
+    let (i, _padding) = complete::take(2usize)(i)?;
+    let (i, ptr_binders) = number::complete::le_u32(i)?;
 
-        if left.is_empty() {
-            break;
-        }
+    Ok((i, (num_args, ptr_binders)))
+}
 
-        left = i;
-    }
+pub fn seek_theorem_entry<'a>(
+    file: &'a [u8],
+    theorems: &'a [u8],
+    idx: u32,
+) -> IResult<'a, TheoremEntry<'a>> {
+    let (entry, _) = take_entries(theorems, idx as u64, 8)?;
+    let (left, (num_args, ptr_binders)) = parse_theorem_header(entry)?;
 
-    Ok((left, ()))
+    let (binders, _) = complete::take(ptr_binders as usize)(file)?;
+    let (unify, binders) = complete::take(num_args as usize * 8)(binders)?;
+
+    let theorem = TheoremEntry { binders, unify };
+
+    Ok((left, theorem))
 }
 
 fn parse_theorem<'a, V: Visitor<'a>>(
     file: &'a [u8],
     input: &'a [u8],
     visitor: &mut V,
-) -> IResult<'a, ()> {
-    let (i, num_args) = number::complete::le_u16(input)?;
-    let (i, _padding) = complete::take(2usize)(i)?;
-    let (i, ptr_binders) = number::complete::le_u32(i)?;
+) -> Visited<'a, ControlFlow<()>, V::Error> {
+    let (_, (num_args, ptr_binders)) = parse_theorem_header(input)?;
 
     let (binders, _) = complete::take(ptr_binders as usize)(file)?;
     let (unify, binders) = complete::take(num_args as usize * 8)(binders)?;
 
-    let offset = {
+    let range = {
         let (slice, offset) = visitor
             .try_reserve_binder_slice(num_args as usize)
             .ok_or(Err::Error(ParseError(input, ErrorType::Memory)))?;
 
         let (_, _) = parse_binders(binders, slice)?;
 
-        offset
+        (offset, offset + slice.len())
     };
 
     let stream = visitor.start_unify_stream();
+    let (_, (unify, unify_indices)) = decode_unify_stream(unify, stream)?;
 
-    let (_, unify) = take_unify_until_end(unify, stream)?;
-
-    let unify_indices = stream.done();
-
-    visitor.parse_theorem((offset, offset + num_args as usize), unify.0, unify_indices);
-
-    Ok((i, ()))
+    visitor
+        .parse_theorem(range, unify, unify_indices)
+        .map_err(VisitError::Visitor)
 }
 
 pub fn parse_sorts<'a, V: Visitor<'a>>(
     input: &'a [u8],
     num_sorts: u8,
     visitor: &mut V,
-) -> IResult<'a, ()> {
-    let mut left = input;
-
-    for _ in 0..num_sorts {
-        let (i, n) = number::complete::le_u8(left)?;
-
-        visitor.parse_sort(From::from(n));
-
-        if left.is_empty() {
-            break;
-        }
-
-        left = i;
+) -> Visited<'a, ControlFlow<()>, V::Error> {
+    for &sort in input.iter().take(num_sorts as usize) {
+        let flow = visitor.parse_sort(From::from(sort));
+        try_visit!(flow.map_err(VisitError::Visitor));
     }
 
-    Ok((left, ()))
+    Ok(ControlFlow::Continue(()))
 }
 
 pub fn parse_theorems<'a, V: Visitor<'a>>(
@@ -305,25 +560,15 @@ pub fn parse_theorems<'a, V: Visitor<'a>>(
     input: &'a [u8],
     num_theorems: usize,
     visitor: &mut V,
-) -> IResult<'a, ()> {
-    let mut parse_theorem = |i| parse_theorem(file, i, visitor);
-
-    let mut left = input;
-
-    for _ in 0..num_theorems {
-        let (i, _) = parse_theorem(left)?;
-
-        if left.is_empty() {
-            break;
-        }
-
-        left = i;
+) -> Visited<'a, ControlFlow<()>, V::Error> {
+    for entry in input.chunks_exact(8).take(num_theorems) {
+        try_visit!(parse_theorem(file, entry, visitor));
     }
 
-    Ok((left, ()))
+    Ok(ControlFlow::Continue(()))
 }
 
-fn parse_skip(input: &[u8]) -> IResult<u32> {
+pub fn parse_skip(input: &[u8]) -> IResult<'_, u32> {
     let (ii, opcode) = number::complete::le_u8(input)?;
 
     if opcode & 0x3F == 0x00 {
@@ -348,9 +593,7 @@ fn parse_skip(input: &[u8]) -> IResult<u32> {
     }
 }
 
-use core::convert::TryFrom;
-
-pub fn parse_opcode<T: TryFrom<u8>>(input: &[u8]) -> IResult<Command<T>> {
+pub fn parse_opcode<T: TryFrom<u8>>(input: &[u8]) -> IResult<'_, Command<T>> {
     let (i, opcode) = number::complete::le_u8(input)?;
     let (i, (operand, _size)) = parse_operand(i, opcode)?;
 
@@ -363,10 +606,31 @@ pub fn parse_opcode<T: TryFrom<u8>>(input: &[u8]) -> IResult<Command<T>> {
     Ok((i, c))
 }
 
+pub fn parse_statement(input: &[u8]) -> IResult<'_, StatementEntry<'_>> {
+    let (left, data) = nom::multi::length_data(parse_skip)(input)?;
+    let (proof, command) = parse_opcode(data)?;
+
+    let statement = StatementEntry {
+        kind: command.opcode,
+        offset: 0,
+        data,
+        proof,
+    };
+
+    Ok((left, statement))
+}
+
+/// Returns whether the input starts with the end command of the statement
+/// stream, which is any command with an opcode of zero.
+pub fn is_stream_end(input: &[u8]) -> bool {
+    matches!(input.first(), Some(opcode) if opcode & 0x3F == 0)
+}
+
 pub fn scan_statement_stream<'a, V: Visitor<'a>>(
     input: &'a [u8],
+    decode_proofs: bool,
     visitor: &mut V,
-) -> IResult<'a, &'a [u8]> {
+) -> Visited<'a, ControlFlow<()>, V::Error> {
     let x = nom::multi::length_data(parse_skip);
     let mut left = input;
     let mut len = 0;
@@ -374,34 +638,139 @@ pub fn scan_statement_stream<'a, V: Visitor<'a>>(
     loop {
         match x(left) {
             Ok((i, o)) => {
-                let (opt_proof, command) = parse_opcode(o)?;
-
-                let indices = if opt_proof.is_empty() {
-                    None
-                } else {
-                    let stream = visitor.start_proof_stream();
-                    take_proof_until_end(opt_proof, stream)?;
-                    Some(stream.done())
-                };
-
-                visitor.parse_statement(From::from(command.opcode), len, o, indices);
+                try_visit!(visit_statement(o, len, decode_proofs, visitor));
 
                 len += o.len();
                 left = i;
             }
-            Err(Err::Error(_)) => {
-                let (_, proofs) = complete::take(len)(input)?;
+            Err(Err::Error(_)) if is_stream_end(left) => {
+                return Ok(ControlFlow::Continue(()));
+            }
+            Err(e) => {
+                return Err(e.into());
+            }
+        }
+    }
+}
+
+/// Visit the statement stream, and visit the table entry of each sort, term
+/// and theorem right before the statement declaring it. Table entries without
+/// a statement are visited after the statement stream, sorts first.
+pub fn visit_declarations<'a, V: Visitor<'a>>(
+    mmb: &Mmb<'a>,
+    decode_proofs: bool,
+    visitor: &mut V,
+) -> Visited<'a, ControlFlow<()>, V::Error> {
+    let x = nom::multi::length_data(parse_skip);
+    let mut left = mmb.proofs;
+    let mut len = 0;
+
+    let lengths = [
+        mmb.header.num_sorts as u64,
+        mmb.header.num_terms as u64,
+        mmb.header.num_theorems as u64,
+    ];
+    let mut counts = [0u64; 3];
 
-                return Ok((left, proofs));
+    loop {
+        match x(left) {
+            Ok((i, o)) => {
+                let (_, command) = parse_opcode(o)?;
+
+                if let Some(kind) = declared_kind(command.opcode) {
+                    let idx = counts[kind as usize];
+                    counts[kind as usize] += 1;
+
+                    if idx < lengths[kind as usize] {
+                        try_visit!(visit_item(mmb, kind, idx, visitor));
+                    }
+                }
+
+                try_visit!(visit_statement(o, len, decode_proofs, visitor));
+
+                len += o.len();
+                left = i;
             }
+            Err(Err::Error(_)) if is_stream_end(left) => break,
             Err(e) => {
-                return Err(e);
+                return Err(e.into());
             }
         }
     }
+
+    for &kind in &[Kind::Sort, Kind::Term, Kind::Theorem] {
+        for idx in counts[kind as usize]..lengths[kind as usize] {
+            try_visit!(visit_item(mmb, kind, idx, visitor));
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
 }
 
-fn take_proof_until_end<'a, S: ProofStream>(input: &'a [u8], stream: &mut S) -> IResult<'a, ()> {
+/// Visit the table entry of a single sort, term or theorem.
+fn visit_item<'a, V: Visitor<'a>>(
+    mmb: &Mmb<'a>,
+    kind: Kind,
+    idx: u64,
+    visitor: &mut V,
+) -> Visited<'a, ControlFlow<()>, V::Error> {
+    match kind {
+        Kind::Sort => {
+            let (entry, _) = take_bytes(mmb.sorts, idx)?;
+            let (_, sort) = number::complete::le_u8(entry)?;
+
+            visitor
+                .parse_sort(From::from(sort))
+                .map_err(VisitError::Visitor)
+        }
+        Kind::Term => {
+            let (entry, _) = take_entries(mmb.terms, idx, 8)?;
+
+            parse_term(mmb.file, entry, visitor)
+        }
+        Kind::Theorem => {
+            let (entry, _) = take_entries(mmb.theorems, idx, 8)?;
+
+            parse_theorem(mmb.file, entry, visitor)
+        }
+    }
+}
+
+/// Visit a single statement, decoding its proof stream if `decode_proofs` is
+/// set.
+fn visit_statement<'a, V: Visitor<'a>>(
+    statement: &'a [u8],
+    offset: usize,
+    decode_proofs: bool,
+    visitor: &mut V,
+) -> Visited<'a, ControlFlow<()>, V::Error> {
+    let (opt_proof, command) = parse_opcode::<Statement>(statement)?;
+
+    let indices = if opt_proof.is_empty() || !decode_proofs {
+        None
+    } else {
+        match visitor.start_proof_stream() {
+            Some(stream) => {
+                stream.start();
+                take_proof_until_end(opt_proof, stream)?;
+                Some(stream.done())
+            }
+            None => {
+                take_proof_until_end(opt_proof, &mut Discard)?;
+                Some(ProofStream::done(&Discard))
+            }
+        }
+    };
+
+    visitor
+        .parse_statement(From::from(command.opcode), offset, statement, indices)
+        .map_err(VisitError::Visitor)
+}
+
+pub fn take_proof_until_end<'a, S: ProofStream>(
+    input: &'a [u8],
+    stream: &mut S,
+) -> IResult<'a, ()> {
     let mut i = input;
 
     loop {
@@ -418,7 +787,7 @@ fn take_proof_until_end<'a, S: ProofStream>(input: &'a [u8], stream: &mut S) ->
     Ok((i, ()))
 }
 
-fn take_magic(input: &[u8]) -> IResult<()> {
+fn take_magic(input: &[u8]) -> IResult<'_, ()> {
     let (rem, _) = complete::tag([0x4d, 0x4d, 0x30, 0x42])(input)?;
 
     Ok((rem, ()))
@@ -435,7 +804,7 @@ pub struct Command {
 }
 */
 
-pub fn parse_unify_opcode(input: &[u8]) -> IResult<Command<Unify>> {
+pub fn parse_unify_opcode(input: &[u8]) -> IResult<'_, Command<Unify>> {
     let (i, opcode) = number::complete::le_u8(input)?;
     let (i, (operand, _size)) = parse_operand(i, opcode)?;
 
@@ -460,7 +829,7 @@ fn take_unify_until_end<'a, S: UnifyStream>(
         i = left;
         counter += 1;
 
-        stream.push(From::from(command));
+        stream.push(command);
 
         if let Unify::End = command.opcode {
             break;
@@ -472,6 +841,27 @@ fn take_unify_until_end<'a, S: UnifyStream>(
     Ok((x, (code, counter)))
 }
 
+/// Decode a unify stream into the given stream, or discard it if there is none,
+/// and return the slice of the unify stream with the indices of the stream.
+fn decode_unify_stream<'a, S: UnifyStream>(
+    input: &'a [u8],
+    stream: Option<&mut S>,
+) -> IResult<'a, (&'a [u8], (usize, usize))> {
+    match stream {
+        Some(stream) => {
+            stream.start();
+            let (left, (unify, _)) = take_unify_until_end(input, stream)?;
+
+            Ok((left, (unify, stream.done())))
+        }
+        None => {
+            let (left, (unify, _)) = take_unify_until_end(input, &mut Discard)?;
+
+            Ok((left, (unify, UnifyStream::done(&Discard))))
+        }
+    }
+}
+
 fn parse_operand<'a, E: nom::error::ParseError<&'a [u8]>>(
     input: &'a [u8],
     opcode: u8,
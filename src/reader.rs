@@ -0,0 +1,462 @@
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::ControlFlow;
+
+use crate::header::{Header, HEADER_SIZE};
+use crate::index::Index;
+use crate::parser::{self, Window};
+use crate::statement::StatementEntry;
+use crate::version::Compatibility;
+use crate::visitor::{VisitError, Visitor};
+
+/// An error that occurred while reading a proof file incrementally.
+#[derive(Debug)]
+pub enum StreamError {
+    /// The underlying reader returned an error.
+    Io(io::Error),
+    /// The file header could not be parsed.
+    InvalidHeader,
+    /// The file has a version that is not accepted by the chosen
+    /// `Compatibility`.
+    UnsupportedVersion(u8),
+    /// The statement at the given offset relative to the start of the proof
+    /// section could not be parsed.
+    InvalidStatement { offset: usize },
+    /// The index could not be parsed.
+    InvalidIndex,
+}
+
+impl From<io::Error> for StreamError {
+    fn from(error: io::Error) -> StreamError {
+        StreamError::Io(error)
+    }
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamError::Io(_) => write!(f, "failed to read the proof file"),
+            StreamError::InvalidHeader => write!(f, "malformed file header or tables"),
+            StreamError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            StreamError::InvalidStatement { offset } => {
+                write!(
+                    f,
+                    "malformed statement at offset {} of the proof section",
+                    offset
+                )
+            }
+            StreamError::InvalidIndex => write!(f, "malformed index"),
+        }
+    }
+}
+
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A reader that parses a proof file incrementally.
+///
+/// Only the part of the file in front of the proof section is read into memory
+/// up front. This contains the header, the sort, term and theorem tables, and
+/// the binders and unify streams of the terms and theorems. The statements of
+/// the proof section are then read one at a time, so that the file can be
+/// processed while it is being downloaded or piped from a compiler, without
+/// buffering it entirely.
+///
+/// The reader must be positioned at the start of the file, and statements are
+/// read without seeking it. If the reader also implements `Seek`, the index at
+/// the end of the file can be read with `read_index`.
+pub struct StreamReader<R> {
+    reader: R,
+    header: Header,
+    tables: Vec<u8>,
+    buffer: Vec<u8>,
+    offset: usize,
+    done: bool,
+}
+
+impl<R: Read> StreamReader<R> {
+    /// Build a `StreamReader` by reading everything in front of the proof
+    /// section, accepting only the supported versions.
+    pub fn new(reader: R) -> Result<StreamReader<R>, StreamError> {
+        StreamReader::with_compatibility(reader, Compatibility::Strict)
+    }
+
+    /// Build a `StreamReader` by reading everything in front of the proof
+    /// section, accepting the versions allowed by the given compatibility
+    /// policy.
+    pub fn with_compatibility(
+        mut reader: R,
+        compatibility: Compatibility,
+    ) -> Result<StreamReader<R>, StreamError> {
+        let mut tables = vec![0; HEADER_SIZE];
+        reader.read_exact(&mut tables)?;
+
+        let (_, header) = parser::parse_header(&tables).map_err(|_| StreamError::InvalidHeader)?;
+
+        compatibility
+            .check(header.version)
+            .map_err(|_| StreamError::UnsupportedVersion(header.version))?;
+
+        let len = (header.proofs_ptr as usize)
+            .checked_sub(HEADER_SIZE)
+            .ok_or(StreamError::InvalidHeader)?;
+
+        read_exactly(&mut reader, &mut tables, len)?;
+
+        parser::parse_tables(&tables, &header).map_err(|_| StreamError::InvalidHeader)?;
+
+        Ok(StreamReader {
+            reader,
+            header,
+            tables,
+            buffer: Vec::new(),
+            offset: 0,
+            done: false,
+        })
+    }
+
+    /// Return the file header
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Return the slice containing everything in front of the proof section
+    pub fn tables(&self) -> &[u8] {
+        &self.tables
+    }
+
+    /// Return the offset of the next statement relative to the start of the
+    /// proof section
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Visit the sorts, terms and theorems, which are all available without
+    /// reading the proof section.
+    pub fn visit_tables<'a, V: Visitor<'a>>(
+        &'a self,
+        visitor: &mut V,
+    ) -> Result<ControlFlow<()>, VisitError<'a, V::Error>> {
+        let file = &self.tables[..];
+        let header = &self.header;
+
+        let (_, (sorts, terms, theorems)) = parser::parse_tables(file, header)?;
+
+        let flow = parser::parse_sorts(sorts, header.num_sorts, visitor)?;
+
+        if flow.is_break() {
+            return Ok(flow);
+        }
+
+        let flow = parser::parse_terms(file, terms, header.num_terms as usize, visitor)?;
+
+        if flow.is_break() {
+            return Ok(flow);
+        }
+
+        parser::parse_theorems(file, theorems, header.num_theorems as usize, visitor)
+    }
+
+    /// Read the next statement of the proof section, or return `None` at the
+    /// end of the statement stream.
+    ///
+    /// The returned statement borrows an internal buffer, which is reused for
+    /// the next statement.
+    pub fn next_statement(&mut self) -> Result<Option<StatementEntry<'_>>, StreamError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        self.buffer.clear();
+        read_exactly(&mut self.reader, &mut self.buffer, 1)?;
+
+        let opcode = self.buffer[0];
+
+        if opcode & 0x3F == 0x00 {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let offset = self.offset;
+        let invalid = || StreamError::InvalidStatement { offset };
+
+        let width = [0, 1, 2, 4][(opcode >> 6) as usize];
+        read_exactly(&mut self.reader, &mut self.buffer, width)?;
+
+        let (_, len) = parser::parse_skip(&self.buffer).map_err(|_| invalid())?;
+
+        let rest = (len as usize)
+            .checked_sub(self.buffer.len())
+            .ok_or_else(invalid)?;
+
+        read_exactly(&mut self.reader, &mut self.buffer, rest)?;
+
+        let (_, mut statement) = parser::parse_statement(&self.buffer).map_err(|_| invalid())?;
+
+        statement.offset = offset;
+        self.offset += self.buffer.len();
+
+        Ok(Some(statement))
+    }
+
+    /// Return the underlying reader
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read + Seek> StreamReader<R> {
+    /// Read the index at the end of the file, or return `None` if the file has
+    /// no index.
+    ///
+    /// The reader is seeked to the index and back, so statements can still be
+    /// read afterwards. Only the part of the file starting at the index or at
+    /// the first table of the index this crate knows is read, which usually
+    /// excludes the proof section. A known table in front of the proof section,
+    /// such as one with a null pointer, is rejected with
+    /// `StreamError::InvalidIndex` instead of reading the entire file.
+    pub fn read_index(&mut self) -> Result<Option<OwnedIndex>, StreamError> {
+        if self.header.index_ptr == 0 {
+            return Ok(None);
+        }
+
+        let position = self.reader.stream_position()?;
+        let index = self.read_index_data();
+        self.reader.seek(SeekFrom::Start(position))?;
+
+        index.map(Some)
+    }
+
+    fn read_index_data(&mut self) -> Result<OwnedIndex, StreamError> {
+        let index_ptr = self.header.index_ptr;
+
+        let mut entries = Vec::new();
+        self.reader.seek(SeekFrom::Start(index_ptr))?;
+        read_exactly(&mut self.reader, &mut entries, 8)?;
+
+        let num = u64::from_le_bytes(entries[..].try_into().unwrap());
+        let len = num
+            .checked_mul(16)
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or(StreamError::InvalidIndex)?;
+
+        entries.clear();
+        read_exactly(&mut self.reader, &mut entries, len)?;
+
+        // Tables usually follow the proof section, but may be anywhere after
+        // it, such as in front of a new index appended to the file.
+        let proofs_ptr = self.header.proofs_ptr as u64;
+        let mut base = index_ptr;
+        let mut left = &entries[..];
+
+        while let Ok((rest, entry)) = parser::parse_index_entry(left) {
+            if entry.is_known() {
+                if entry.ptr() < proofs_ptr {
+                    return Err(StreamError::InvalidIndex);
+                }
+
+                base = base.min(entry.ptr());
+            }
+
+            left = rest;
+        }
+
+        let mut data = Vec::new();
+        self.reader.seek(SeekFrom::Start(base))?;
+        self.reader.read_to_end(&mut data)?;
+
+        parser::parse_index(Window::new(&data, base), &self.header)
+            .map_err(|_| StreamError::InvalidIndex)?;
+
+        Ok(OwnedIndex {
+            header: self.header,
+            base,
+            data,
+        })
+    }
+}
+
+/// The index of a proof file, read by `StreamReader::read_index`.
+///
+/// This owns the part of the file containing the index and its tables.
+#[derive(Debug)]
+pub struct OwnedIndex {
+    header: Header,
+    base: u64,
+    data: Vec<u8>,
+}
+
+impl OwnedIndex {
+    /// Return the index, which gives access to the name table, source spans
+    /// and proof source map of the file
+    pub fn index(&self) -> Index<'_> {
+        parser::parse_index(Window::new(&self.data, self.base), &self.header)
+            .map(|(_, index)| index)
+            .expect("index was parsed when it was read")
+    }
+}
+
+/// Append exactly `len` bytes from the reader to the buffer.
+///
+/// The buffer grows as data arrives, so that a corrupt length cannot cause a
+/// large allocation up front.
+fn read_exactly<R: Read>(reader: &mut R, buffer: &mut Vec<u8>, len: usize) -> io::Result<()> {
+    let read = reader.by_ref().take(len as u64).read_to_end(buffer)?;
+
+    if read < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::index::name_table::write_sorted_names;
+    use crate::index::source_spans::{SourceSpan, SourceSpanWriter};
+    use crate::index::Kind;
+    use crate::testing;
+    use crate::Mmb;
+
+    fn statements<R: Read>(reader: &mut StreamReader<R>) -> usize {
+        let mut num = 0;
+
+        while reader.next_statement().unwrap().is_some() {
+            num += 1;
+        }
+
+        num
+    }
+
+    #[test]
+    fn index_is_read_between_statements() {
+        let file = testing::logic().build();
+        let mut reader = StreamReader::new(Cursor::new(&file)).unwrap();
+
+        assert!(reader.next_statement().unwrap().is_some());
+
+        let index = reader.read_index().unwrap().unwrap();
+        let names = index.index().name_table().unwrap();
+        assert_eq!(
+            names.find_kind(Kind::Term, b"im").map(|(idx, _)| idx),
+            Some(0)
+        );
+        assert_eq!(
+            names.find_kind(Kind::Theorem, b"ax").map(|(idx, _)| idx),
+            Some(0)
+        );
+
+        // The proof section is not read again.
+        assert!(index.base >= reader.header().proofs_ptr as u64);
+
+        assert_eq!(statements(&mut reader), 3);
+    }
+
+    #[test]
+    fn appended_tables_are_read() {
+        let file = testing::logic().build();
+        let mut writer = SourceSpanWriter::new();
+        let path = writer.add_file(b"a.mm1").unwrap();
+        let span = SourceSpan {
+            file: path,
+            line: 2,
+            column: 1,
+        };
+        writer.set(Kind::Term, 0, span).unwrap();
+
+        let file = writer.write(&Mmb::from(&file).unwrap());
+        let file = write_sorted_names(&Mmb::from(&file).unwrap()).unwrap();
+
+        let mut reader = StreamReader::new(Cursor::new(&file)).unwrap();
+        let index = reader.read_index().unwrap().unwrap();
+        let index = index.index();
+
+        assert!(index.name_table().unwrap().is_sorted());
+        assert_eq!(index.source_spans().unwrap().get(Kind::Term, 0), Some(span));
+        assert_eq!(
+            index.source_spans().unwrap().file_path(path),
+            Some(&b"a.mm1"[..])
+        );
+
+        assert_eq!(statements(&mut reader), 4);
+    }
+
+    #[test]
+    fn files_without_index() {
+        let mut file = testing::logic().build();
+        file[32..40].copy_from_slice(&0u64.to_le_bytes());
+
+        let mut reader = StreamReader::new(Cursor::new(&file)).unwrap();
+        assert!(reader.read_index().unwrap().is_none());
+    }
+
+    #[test]
+    fn malformed_indexes_are_errors() {
+        let mut file = testing::logic().build();
+        let len = file.len() as u64;
+        file[32..40].copy_from_slice(&(len - 4).to_le_bytes());
+
+        let mut reader = StreamReader::new(Cursor::new(&file)).unwrap();
+        assert!(matches!(reader.read_index(), Err(StreamError::Io(_))));
+        assert_eq!(statements(&mut reader), 4);
+    }
+
+    #[test]
+    fn tables_in_front_of_the_proof_section_are_rejected() {
+        let mut file = testing::logic().build();
+        let entry = testing::index_ptr(&file) + 8;
+        file[entry + 8..entry + 16].copy_from_slice(&0u64.to_le_bytes());
+
+        let mut reader = StreamReader::new(Cursor::new(&file)).unwrap();
+        assert!(matches!(
+            reader.read_index(),
+            Err(StreamError::InvalidIndex)
+        ));
+        assert_eq!(statements(&mut reader), 4);
+    }
+
+    #[test]
+    fn malformed_statements_are_errors() {
+        let mut file = testing::logic().build();
+        let proofs_ptr = Mmb::from(&file).unwrap().proofs_ptr();
+
+        // The third statement has an opcode that is not a statement.
+        file[proofs_ptr + 10] = 0xFF;
+
+        let mut reader = StreamReader::new(Cursor::new(&file)).unwrap();
+        assert!(reader.next_statement().unwrap().is_some());
+        assert!(reader.next_statement().unwrap().is_some());
+
+        let error = reader.next_statement().unwrap_err();
+        assert!(matches!(
+            error,
+            StreamError::InvalidStatement { offset: 10 }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "malformed statement at offset 10 of the proof section"
+        );
+        assert_eq!(reader.offset(), 10);
+    }
+
+    #[test]
+    fn io_errors_are_the_source() {
+        let file = testing::logic().build();
+        let error = StreamReader::new(Cursor::new(&file[..20])).err().unwrap();
+
+        assert!(matches!(error, StreamError::Io(_)));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+}
@@ -0,0 +1,359 @@
+//! Serde support for the types of `mmb-types`, which has no `serde` feature of
+//! its own.
+//!
+//! Opcodes are serialized by the names of their variants, and commands as
+//! structs with an `opcode` and an `operand` field.
+//!
+//! Names borrowed from the proof file are serialized as strings if they are
+//! valid UTF-8, and as byte strings otherwise. They are borrowed from the input
+//! again when deserializing, which works for strings without escape sequences
+//! in formats like JSON, and for byte strings in binary formats.
+
+use core::fmt;
+
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::opcode::{Command, Proof, Statement, Unify};
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Statement")]
+pub(crate) enum StatementDef {
+    End,
+    Axiom,
+    Sort,
+    TermDef,
+    Thm,
+    LocalDef,
+    LocalTerm,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Unify")]
+enum UnifyDef {
+    End,
+    Term,
+    TermSave,
+    Ref,
+    Dummy,
+    Hyp,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Proof")]
+enum ProofDef {
+    End,
+    Term,
+    TermSave,
+    Ref,
+    Dummy,
+    Thm,
+    ThmSave,
+    Hyp,
+    Conv,
+    Refl,
+    Symm,
+    Cong,
+    Unfold,
+    ConvCut,
+    ConvRef,
+    ConvSave,
+    Save,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Command<Unify>")]
+struct UnifyCommandDef {
+    #[serde(with = "UnifyDef")]
+    opcode: Unify,
+    operand: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Command<Proof>")]
+struct ProofCommandDef {
+    #[serde(with = "ProofDef")]
+    opcode: Proof,
+    operand: u32,
+}
+
+/// Define a module for use with `#[serde(with = "...")]` on a vector of
+/// commands, given the remote definition of a single command.
+macro_rules! command_vec {
+    ($name:ident, $opcode:ty, $def:ident) => {
+        pub(crate) mod $name {
+            use alloc::vec::Vec;
+
+            use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+            use super::$def;
+            use crate::opcode::Command;
+
+            struct Ser<'a>(&'a Command<$opcode>);
+
+            impl Serialize for Ser<'_> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    $def::serialize(self.0, serializer)
+                }
+            }
+
+            struct De(Command<$opcode>);
+
+            impl<'de> Deserialize<'de> for De {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<De, D::Error> {
+                    $def::deserialize(deserializer).map(De)
+                }
+            }
+
+            pub(crate) fn serialize<S: Serializer>(
+                commands: &[Command<$opcode>],
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(commands.iter().map(Ser))
+            }
+
+            pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Vec<Command<$opcode>>, D::Error> {
+                let commands = Vec::<De>::deserialize(deserializer)?;
+
+                Ok(commands.into_iter().map(|De(command)| command).collect())
+            }
+        }
+    };
+}
+
+command_vec!(unify_commands, crate::opcode::Unify, UnifyCommandDef);
+command_vec!(proof_commands, crate::opcode::Proof, ProofCommandDef);
+
+/// A name borrowed from the proof file, serialized as described in the module
+/// documentation.
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match core::str::from_utf8(self.0) {
+            Ok(name) => serializer.serialize_str(name),
+            Err(_) => serializer.serialize_bytes(self.0),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Bytes<'de>, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> Visitor<'de> for BytesVisitor {
+            type Value = Bytes<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a borrowed string or byte string")
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(Bytes(v.as_bytes()))
+            }
+
+            fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+                Ok(Bytes(v))
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+/// For use with `#[serde(with = "...")]` on a borrowed name.
+pub(crate) mod bytes {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Bytes;
+
+    pub(crate) fn serialize<S: Serializer>(name: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        Bytes(name).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<&'de [u8], D::Error> {
+        Bytes::deserialize(deserializer).map(|Bytes(name)| name)
+    }
+}
+
+/// For use with `#[serde(with = "...")]` on an optional borrowed name.
+pub(crate) mod option_bytes {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Bytes;
+
+    pub(crate) fn serialize<S: Serializer>(
+        name: &Option<&[u8]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        name.map(Bytes).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<&'de [u8]>, D::Error> {
+        let name = Option::<Bytes>::deserialize(deserializer)?;
+
+        Ok(name.map(|Bytes(name)| name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::index::name_table::{Kind, Name};
+    use crate::index::proof_map::ProofStep;
+    use crate::index::source_spans::SourceSpan;
+    use crate::model::MmbModel;
+    use crate::opcode::{Proof, Unify};
+    use crate::testing::{self, Builder};
+    use crate::Mmb;
+
+    fn file() -> Vec<u8> {
+        let mut builder = Builder::new();
+        let wff = builder.sort("wff");
+        let im = builder.term("im", wff, &[0, 0]);
+
+        let unify = testing::unify(&[(Unify::TermSave, im), (Unify::Ref, 0), (Unify::Ref, 1)]);
+        let ax = builder.axiom("ax", &[0], unify.clone(), Vec::new());
+
+        builder.theorem(
+            "thm",
+            &[0],
+            unify,
+            testing::proof(&[
+                (Proof::Ref, 0),
+                (Proof::Ref, 0),
+                (Proof::TermSave, im),
+                (Proof::Ref, 1),
+                (Proof::Thm, ax),
+            ]),
+        );
+
+        builder.build()
+    }
+
+    #[test]
+    fn model_round_trip() {
+        let file = file();
+        let model = MmbModel::new(&Mmb::from(&file).unwrap()).unwrap();
+
+        let json = serde_json::to_string(&model).unwrap();
+        assert_eq!(serde_json::from_str::<MmbModel>(&json).unwrap(), model);
+
+        for term in &model.terms {
+            let json = serde_json::to_string(term).unwrap();
+            assert_eq!(
+                &serde_json::from_str::<crate::model::Term>(&json).unwrap(),
+                term
+            );
+        }
+
+        for theorem in &model.theorems {
+            let json = serde_json::to_string(theorem).unwrap();
+            assert_eq!(
+                &serde_json::from_str::<crate::model::Theorem>(&json).unwrap(),
+                theorem
+            );
+        }
+
+        for statement in &model.statements {
+            let json = serde_json::to_string(statement).unwrap();
+            assert_eq!(
+                &serde_json::from_str::<crate::model::Statement>(&json).unwrap(),
+                statement
+            );
+        }
+    }
+
+    #[test]
+    fn kind_round_trip() {
+        for kind in &[Kind::Sort, Kind::Term, Kind::Theorem] {
+            let json = serde_json::to_string(kind).unwrap();
+            assert_eq!(&serde_json::from_str::<Kind>(&json).unwrap(), kind);
+        }
+    }
+
+    #[test]
+    fn name_round_trip() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+        let names = mmb.index().unwrap().name_table().unwrap();
+
+        for name in names.iter() {
+            let json = serde_json::to_string(&name).unwrap();
+            assert_eq!(serde_json::from_str::<Name>(&json).unwrap(), name);
+        }
+
+        let name = names.theorems().get(0).unwrap();
+        let json = serde_json::to_string(&name).unwrap();
+        assert_eq!(
+            json,
+            alloc::format!(r#"{{"ptr":{},"name":"ax"}}"#, name.ptr)
+        );
+    }
+
+    #[test]
+    fn escaped_names_are_rejected() {
+        let name = |name| alloc::format!(r#"{{"ptr":0,"name":"{}"}}"#, name);
+        assert!(serde_json::from_str::<Name>(&name("ax")).is_ok());
+        assert!(serde_json::from_str::<Name>(&name(r"a\u0078")).is_err());
+
+        let step = |name| {
+            alloc::format!(
+                r#"{{"offset":0,"span":{{"file":0,"line":0,"column":0}},"name":"{}"}}"#,
+                name
+            )
+        };
+        assert!(serde_json::from_str::<ProofStep>(&step("h1")).is_ok());
+        assert!(serde_json::from_str::<ProofStep>(&step(r"h\u0031")).is_err());
+    }
+
+    #[test]
+    fn source_span_round_trip() {
+        let span = SourceSpan {
+            file: 1,
+            line: 2,
+            column: 3,
+        };
+
+        let json = serde_json::to_string(&span).unwrap();
+        assert_eq!(serde_json::from_str::<SourceSpan>(&json).unwrap(), span);
+    }
+
+    #[test]
+    fn proof_step_round_trip() {
+        let span = SourceSpan {
+            file: 0,
+            line: 4,
+            column: 2,
+        };
+
+        for &name in &[Some(&b"h1"[..]), None] {
+            let step = ProofStep {
+                offset: 7,
+                span,
+                name,
+            };
+
+            let json = serde_json::to_string(&step).unwrap();
+            assert_eq!(serde_json::from_str::<ProofStep>(&json).unwrap(), step);
+        }
+    }
+
+    #[test]
+    fn names_that_are_not_utf8_are_byte_strings() {
+        let name = Name {
+            ptr: 0,
+            name: b"\xff",
+        };
+
+        let json = serde_json::to_string(&name).unwrap();
+        assert_eq!(json, r#"{"ptr":0,"name":[255]}"#);
+    }
+}
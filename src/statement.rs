@@ -0,0 +1,379 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::command::{CommandIter, ProofCommandIter};
+#[cfg(feature = "alloc")]
+use crate::error::ParseError;
+use crate::index::Kind;
+use crate::opcode::Statement;
+use crate::parser;
+#[cfg(feature = "alloc")]
+use crate::visitor::ProofStream;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// A statement in the proof section.
+///
+/// Every statement starts with a statement command whose operand is the length
+/// of the statement, followed by the proof stream of the statement, if any.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StatementEntry<'a> {
+    /// The kind of the statement.
+    pub kind: Statement,
+    /// The offset of the statement relative to the start of the proof section.
+    pub offset: usize,
+    /// The slice containing the entire statement, including the statement
+    /// command.
+    pub data: &'a [u8],
+    /// The slice containing the proof stream of the statement, which is empty
+    /// if the statement has no proof stream.
+    pub proof: &'a [u8],
+}
+
+impl<'a> StatementEntry<'a> {
+    /// Returns an iterator over the commands of the proof stream of the
+    /// statement, with offsets relative to the start of the proof section.
+    pub fn proof_commands(&self) -> ProofCommandIter<'a> {
+        let offset = self.offset + self.data.len() - self.proof.len();
+
+        CommandIter::new(self.proof, offset)
+    }
+}
+
+/// An iterator over the statements in the proof section, together with the
+/// kind of the declared item and its index in the table of that kind.
+///
+/// The n-th statement declaring an item of some kind declares the n-th entry of
+/// the table of that kind.
+pub struct DeclarationIterator<'a> {
+    statements: StatementIterator<'a>,
+    counts: [u64; 3],
+}
+
+impl<'a> DeclarationIterator<'a> {
+    pub(crate) fn new(proofs: &'a [u8]) -> DeclarationIterator<'a> {
+        DeclarationIterator {
+            statements: StatementIterator::new(proofs),
+            counts: [0; 3],
+        }
+    }
+
+    /// Returns the statement that could not be parsed if the iterator stopped
+    /// before the end of the statement stream, or `None` otherwise.
+    pub fn error(&self) -> Option<MalformedStatement> {
+        self.statements.error()
+    }
+}
+
+impl<'a> Iterator for DeclarationIterator<'a> {
+    type Item = (Kind, u64, StatementEntry<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let statement = self.statements.next()?;
+        let kind = declared_kind(statement.kind)?;
+
+        let idx = self.counts[kind as usize];
+        self.counts[kind as usize] += 1;
+
+        Some((kind, idx, statement))
+    }
+}
+
+/// Returns the kind of item declared by a statement, or `None` for the end of
+/// the statement stream.
+pub fn declared_kind(statement: Statement) -> Option<Kind> {
+    match statement {
+        Statement::Sort => Some(Kind::Sort),
+        Statement::TermDef | Statement::LocalDef => Some(Kind::Term),
+        // `Statement::LocalTerm` is the opcode of local theorems
+        Statement::Axiom | Statement::Thm | Statement::LocalTerm => Some(Kind::Theorem),
+        Statement::End => None,
+    }
+}
+
+/// An iterator over the statements in the proof section.
+///
+/// The iterator stops at the end of the statement stream, or at the first
+/// statement that cannot be parsed, which is then reported by `error`.
+pub struct StatementIterator<'a> {
+    left: &'a [u8],
+    offset: usize,
+    error: Option<MalformedStatement>,
+}
+
+/// A statement that could not be parsed, which stopped an iterator over the
+/// statements before the end of the statement stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MalformedStatement {
+    /// The offset of the statement relative to the start of the proof section.
+    pub offset: usize,
+}
+
+impl<'a> StatementIterator<'a> {
+    pub(crate) fn new(proofs: &'a [u8]) -> StatementIterator<'a> {
+        StatementIterator {
+            left: proofs,
+            offset: 0,
+            error: None,
+        }
+    }
+
+    /// Returns the statement that could not be parsed if the iterator stopped
+    /// before the end of the statement stream, or `None` otherwise.
+    ///
+    /// Check this after the iterator is exhausted to tell a complete proof
+    /// section from a truncated one.
+    pub fn error(&self) -> Option<MalformedStatement> {
+        self.error
+    }
+
+    /// Returns the offset of the next statement relative to the start of the
+    /// proof section.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the part of the proof section that has not been parsed yet.
+    ///
+    /// After the iterator is exhausted, this starts with the end command of
+    /// the statement stream if the entire stream was parsed successfully.
+    pub fn rest(&self) -> &'a [u8] {
+        self.left
+    }
+}
+
+impl<'a> Iterator for StatementIterator<'a> {
+    type Item = StatementEntry<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (left, mut statement) = match parser::parse_statement(self.left) {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                // Anything but the end command is a statement that cannot be
+                // parsed, including the end of the proof section.
+                if !parser::is_stream_end(self.left) {
+                    self.error = Some(MalformedStatement {
+                        offset: self.offset,
+                    });
+                }
+
+                return None;
+            }
+        };
+
+        statement.offset = self.offset;
+
+        self.offset += statement.data.len();
+        self.left = left;
+
+        Some(statement)
+    }
+}
+
+/// The proof streams of a chunk of consecutive statements, decoded into a
+/// single sink.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct DecodedChunk<S> {
+    /// The index of the first statement of the chunk.
+    pub first: usize,
+    /// The sink that received the proof commands of the chunk.
+    pub sink: S,
+    /// The indices returned by `ProofStream::done` for each statement of the
+    /// chunk, or `None` for statements without a proof stream.
+    pub proofs: Vec<Option<(usize, usize)>>,
+}
+
+/// Decode the proof streams of the given statements in order into `sink`.
+///
+/// Returns the indices returned by `ProofStream::done` for each statement, or
+/// `None` for statements without a proof stream.
+#[cfg(feature = "alloc")]
+pub fn decode_proofs<'a, S: ProofStream>(
+    statements: &[StatementEntry<'a>],
+    sink: &mut S,
+) -> Result<Vec<Option<(usize, usize)>>, nom::Err<ParseError<'a>>> {
+    statements
+        .iter()
+        .map(|statement| {
+            if statement.proof.is_empty() {
+                return Ok(None);
+            }
+
+            sink.start();
+            parser::take_proof_until_end(statement.proof, sink)?;
+
+            Ok(Some(sink.done()))
+        })
+        .collect()
+}
+
+/// Decode the proof streams of the given statements in parallel.
+///
+/// Because every statement is prefixed by its length, the statements can be
+/// split sequentially with `Mmb::statements` first. That iterator stops at the
+/// first statement that cannot be parsed, so check `StatementIterator::error`
+/// after collecting the statements: the statements after it are never decoded.
+///
+/// The statements are decoded in chunks of `chunk_size` consecutive
+/// statements, and the chunks are returned in order. Every chunk is decoded on
+/// a worker thread into a fresh sink created by `init`, so there is one sink
+/// per chunk, not one per thread. To get about one sink per thread, pass the
+/// number of statements divided by `rayon::current_num_threads()` as
+/// `chunk_size`.
+#[cfg(feature = "rayon")]
+pub fn par_decode_proofs<'a, S, F>(
+    statements: &[StatementEntry<'a>],
+    chunk_size: usize,
+    init: F,
+) -> Result<Vec<DecodedChunk<S>>, nom::Err<ParseError<'a>>>
+where
+    S: ProofStream + Send,
+    F: Fn() -> S + Sync + Send,
+{
+    let chunk_size = chunk_size.max(1);
+
+    statements
+        .par_chunks(chunk_size)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let mut sink = init();
+            let proofs = decode_proofs(chunk, &mut sink)?;
+
+            Ok(DecodedChunk {
+                first: i * chunk_size,
+                sink,
+                proofs,
+            })
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use core::convert::TryInto;
+
+    use super::*;
+    use crate::opcode::{Command, Proof, Unify};
+    use crate::testing;
+    use crate::Mmb;
+
+    /// Add three theorems with the same proof to `testing::logic`.
+    fn file() -> Vec<u8> {
+        let mut builder = testing::logic();
+
+        for name in &["a", "b", "c"] {
+            builder.theorem(
+                name,
+                &[0],
+                testing::unify(&[(Unify::Ref, 0)]),
+                testing::proof(&[(Proof::Ref, 0), (Proof::Ref, 0), (Proof::Thm, 0)]),
+            );
+        }
+
+        builder.build()
+    }
+
+    /// Counts the commands of each proof stream, including the end command.
+    #[derive(Default)]
+    struct Count {
+        commands: usize,
+        start: usize,
+    }
+
+    impl ProofStream for Count {
+        fn start(&mut self) {
+            self.start = self.commands;
+        }
+
+        fn push(&mut self, _command: Command<Proof>) {
+            self.commands += 1;
+        }
+
+        fn done(&self) -> (usize, usize) {
+            (self.start, self.commands)
+        }
+    }
+
+    #[test]
+    fn complete_statement_streams_have_no_error() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+
+        let mut statements = mmb.statements();
+        assert_eq!(statements.by_ref().count(), 7);
+        assert_eq!(statements.error(), None);
+
+        let mut declarations = mmb.declarations();
+        assert_eq!(declarations.by_ref().count(), 7);
+        assert_eq!(declarations.error(), None);
+    }
+
+    #[test]
+    fn malformed_statements_are_reported() {
+        let mut file = file();
+        let proofs_ptr = u32::from_le_bytes(file[24..28].try_into().unwrap()) as usize;
+
+        // The length of the second statement exceeds the file.
+        let second = proofs_ptr + 5;
+        file[second + 1..second + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mmb = Mmb::from(&file).unwrap();
+
+        let mut statements = mmb.statements();
+        assert_eq!(statements.by_ref().count(), 1);
+        assert_eq!(statements.error(), Some(MalformedStatement { offset: 5 }));
+
+        let mut declarations = mmb.declarations();
+        assert_eq!(declarations.by_ref().count(), 1);
+        assert_eq!(declarations.error(), Some(MalformedStatement { offset: 5 }));
+    }
+
+    #[test]
+    fn decoded_proofs() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+        let statements: Vec<_> = mmb.statements().collect();
+
+        let mut sink = Count::default();
+        let proofs = decode_proofs(&statements, &mut sink).unwrap();
+
+        assert_eq!(
+            proofs,
+            [
+                None,
+                None,
+                None,
+                None,
+                Some((0, 4)),
+                Some((4, 8)),
+                Some((8, 12))
+            ]
+        );
+
+        #[cfg(feature = "rayon")]
+        {
+            let chunks = par_decode_proofs(&statements, 2, Count::default).unwrap();
+            let firsts: Vec<_> = chunks.iter().map(|chunk| chunk.first).collect();
+            let proofs: Vec<_> = chunks
+                .iter()
+                .flat_map(|chunk| chunk.proofs.clone())
+                .collect();
+
+            assert_eq!(firsts, [0, 2, 4, 6]);
+            assert_eq!(
+                proofs,
+                [
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some((0, 4)),
+                    Some((4, 8)),
+                    Some((0, 4))
+                ]
+            );
+        }
+    }
+}
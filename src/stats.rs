@@ -0,0 +1,541 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::header::HEADER_SIZE;
+use crate::index::{Kind, NameTable};
+use crate::opcode::{Proof, Statement};
+use crate::statement::StatementEntry;
+use crate::Mmb;
+
+/// The sizes of the sections of a proof file in bytes.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SectionSizes {
+    /// The fixed part of the file header.
+    pub header: usize,
+    /// The sort table.
+    pub sorts: usize,
+    /// The term table.
+    pub terms: usize,
+    /// The theorem table.
+    pub theorems: usize,
+    /// The binders and unify streams of the terms and theorems, including any
+    /// padding in front of the proof section.
+    pub declarations: usize,
+    /// The statement stream of the proof section, up to and including its end
+    /// command.
+    pub proofs: usize,
+    /// Everything after the statement stream, such as the index and its
+    /// tables, which is zero if the file has no index.
+    pub index: usize,
+}
+
+/// The number of statements of each kind in the proof section.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct StatementCounts {
+    pub sorts: usize,
+    pub terms: usize,
+    pub defs: usize,
+    pub local_defs: usize,
+    pub axioms: usize,
+    pub theorems: usize,
+    pub local_theorems: usize,
+}
+
+/// The size of the proof stream of a single statement.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ProofSize {
+    /// The kind of the statement.
+    pub statement: Statement,
+    /// The offset of the statement relative to the start of the proof section.
+    pub offset: usize,
+    /// The kind and index of the item declared by the statement.
+    pub item: (Kind, u64),
+    /// The size of the proof stream in bytes.
+    pub bytes: usize,
+    /// The number of commands in the proof stream.
+    pub commands: usize,
+    /// The largest size of the heap while running the proof stream, counting
+    /// the binders of the item.
+    pub heap: usize,
+}
+
+/// Statistics about a proof file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The sizes of the sections of the file.
+    pub sections: SectionSizes,
+    /// The number of statements of each kind.
+    pub statements: StatementCounts,
+    /// The number of times each term is used by a `Proof::Term` or
+    /// `Proof::TermSave` command, indexed by term.
+    pub term_uses: Vec<u64>,
+    /// The number of times each theorem is used by a `Proof::Thm` or
+    /// `Proof::ThmSave` command, indexed by theorem.
+    pub theorem_uses: Vec<u64>,
+    /// The number of commands in all proof streams with an operand of 0, 1, 2
+    /// and 4 bytes respectively.
+    pub operand_widths: [u64; 4],
+    /// The sizes of the proof streams of all statements that have one, ordered
+    /// from largest to smallest in bytes.
+    pub proofs: Vec<ProofSize>,
+}
+
+impl Stats {
+    /// Compute the statistics of a proof file.
+    ///
+    /// Statements past the first statement that cannot be parsed are not
+    /// counted, and commands past the first command that cannot be parsed do
+    /// not count towards the proof stream of a statement.
+    pub fn new(mmb: &Mmb) -> Stats {
+        let mut stats = Stats {
+            sections: section_sizes(mmb),
+            term_uses: vec![0; mmb.num_terms() as usize],
+            theorem_uses: vec![0; mmb.num_theorems() as usize],
+            ..Default::default()
+        };
+
+        let proofs = mmb.proofs();
+
+        for (kind, idx, statement) in mmb.declarations() {
+            let is_def = kind == Kind::Term
+                && matches!(mmb.term(idx as u32), Some(term) if term.sort & 0x80 != 0);
+
+            stats.count_statement(&statement, is_def);
+
+            if statement.proof.is_empty() {
+                continue;
+            }
+
+            let binders = match kind {
+                Kind::Sort => None,
+                Kind::Term => mmb.term(idx as u32).map(|term| term.num_args()),
+                Kind::Theorem => mmb.theorem(idx as u32).map(|thm| thm.num_args()),
+            };
+
+            let mut size = ProofSize {
+                statement: statement.kind,
+                offset: statement.offset,
+                item: (kind, idx),
+                bytes: statement.proof.len(),
+                commands: 0,
+                heap: binders.unwrap_or(0),
+            };
+
+            let mut heap = size.heap;
+
+            for (offset, command) in statement.proof_commands() {
+                size.commands += 1;
+
+                let width = proofs.get(offset).map_or(0, |opcode| opcode >> 6);
+                stats.operand_widths[width as usize] += 1;
+
+                let operand = command.operand as usize;
+
+                let uses = match command.opcode {
+                    Proof::Term | Proof::TermSave => stats.term_uses.get_mut(operand),
+                    Proof::Thm | Proof::ThmSave => stats.theorem_uses.get_mut(operand),
+                    _ => None,
+                };
+
+                if let Some(count) = uses {
+                    *count += 1;
+                }
+
+                if let Proof::Dummy
+                | Proof::TermSave
+                | Proof::ThmSave
+                | Proof::Hyp
+                | Proof::ConvSave
+                | Proof::Save = command.opcode
+                {
+                    heap += 1;
+                    size.heap = size.heap.max(heap);
+                }
+            }
+
+            stats.proofs.push(size);
+        }
+
+        stats
+            .proofs
+            .sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.offset.cmp(&b.offset)));
+
+        stats
+    }
+
+    fn count_statement(&mut self, statement: &StatementEntry, is_def: bool) {
+        let counts = &mut self.statements;
+
+        match statement.kind {
+            Statement::Sort => counts.sorts += 1,
+            Statement::TermDef if is_def => counts.defs += 1,
+            Statement::TermDef => counts.terms += 1,
+            Statement::LocalDef => counts.local_defs += 1,
+            Statement::Axiom => counts.axioms += 1,
+            Statement::Thm => counts.theorems += 1,
+            Statement::LocalTerm => counts.local_theorems += 1,
+            Statement::End => {}
+        }
+    }
+
+    /// Returns the total number of commands in all proof streams.
+    pub fn total_proof_commands(&self) -> usize {
+        self.proofs.iter().map(|proof| proof.commands).sum()
+    }
+
+    /// Returns the average number of commands per proof stream.
+    pub fn average_proof_commands(&self) -> f64 {
+        if self.proofs.is_empty() {
+            return 0.0;
+        }
+
+        self.total_proof_commands() as f64 / self.proofs.len() as f64
+    }
+
+    /// Returns the largest number of commands in any proof stream.
+    pub fn max_proof_commands(&self) -> usize {
+        self.proofs
+            .iter()
+            .map(|proof| proof.commands)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the largest size of the heap in any proof stream.
+    pub fn max_heap(&self) -> usize {
+        self.proofs
+            .iter()
+            .map(|proof| proof.heap)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the `n` most used terms as pairs of term index and number of
+    /// uses, from most to least used.
+    pub fn most_used_terms(&self, n: usize) -> Vec<(u64, u64)> {
+        most_used(&self.term_uses, n)
+    }
+
+    /// Returns the `n` most used theorems as pairs of theorem index and number
+    /// of uses, from most to least used.
+    pub fn most_used_theorems(&self, n: usize) -> Vec<(u64, u64)> {
+        most_used(&self.theorem_uses, n)
+    }
+
+    /// Returns the `n` largest proof streams in bytes.
+    pub fn largest_proofs(&self, n: usize) -> &[ProofSize] {
+        &self.proofs[..n.min(self.proofs.len())]
+    }
+
+    /// Returns a human readable report of the statistics, which lists the top
+    /// `n` terms, theorems and proofs, labelled with names from the name table
+    /// if one is given.
+    pub fn report<'b>(&'b self, names: Option<&'b NameTable<'b>>, n: usize) -> Report<'b> {
+        Report {
+            stats: self,
+            names,
+            n,
+        }
+    }
+}
+
+/// A human readable report of the statistics of a proof file.
+pub struct Report<'a> {
+    stats: &'a Stats,
+    names: Option<&'a NameTable<'a>>,
+    n: usize,
+}
+
+impl<'a> Report<'a> {
+    fn label(&self, f: &mut fmt::Formatter, kind: Kind, idx: u64) -> fmt::Result {
+        let name = self
+            .names
+            .and_then(|names| names.section(kind).get(idx))
+            .and_then(|name| name.to_str().ok());
+
+        match name {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "#{}", idx),
+        }
+    }
+}
+
+impl<'a> fmt::Display for Report<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stats = self.stats;
+        let s = &stats.sections;
+
+        writeln!(f, "sections:")?;
+        writeln!(f, "  header        {:>12}", s.header)?;
+        writeln!(f, "  sorts         {:>12}", s.sorts)?;
+        writeln!(f, "  terms         {:>12}", s.terms)?;
+        writeln!(f, "  theorems      {:>12}", s.theorems)?;
+        writeln!(f, "  declarations  {:>12}", s.declarations)?;
+        writeln!(f, "  proofs        {:>12}", s.proofs)?;
+        writeln!(f, "  index         {:>12}", s.index)?;
+
+        let c = &stats.statements;
+
+        writeln!(f, "statements:")?;
+        writeln!(f, "  sort          {:>12}", c.sorts)?;
+        writeln!(f, "  term          {:>12}", c.terms)?;
+        writeln!(f, "  def           {:>12}", c.defs)?;
+        writeln!(f, "  local def     {:>12}", c.local_defs)?;
+        writeln!(f, "  axiom         {:>12}", c.axioms)?;
+        writeln!(f, "  theorem       {:>12}", c.theorems)?;
+        writeln!(f, "  local theorem {:>12}", c.local_theorems)?;
+
+        writeln!(f, "proofs:")?;
+        writeln!(f, "  count         {:>12}", stats.proofs.len())?;
+        writeln!(f, "  commands      {:>12}", stats.total_proof_commands())?;
+        writeln!(
+            f,
+            "  average       {:>12.1}",
+            stats.average_proof_commands()
+        )?;
+        writeln!(f, "  maximum       {:>12}", stats.max_proof_commands())?;
+        writeln!(f, "  maximum heap  {:>12}", stats.max_heap())?;
+
+        writeln!(f, "operand widths:")?;
+        for (width, count) in [0, 1, 2, 4].iter().zip(&stats.operand_widths) {
+            writeln!(f, "  {} bytes       {:>12}", width, count)?;
+        }
+
+        writeln!(f, "most used terms:")?;
+        for (idx, count) in stats.most_used_terms(self.n) {
+            write!(f, "  {:>12}  ", count)?;
+            self.label(f, Kind::Term, idx)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "most used theorems:")?;
+        for (idx, count) in stats.most_used_theorems(self.n) {
+            write!(f, "  {:>12}  ", count)?;
+            self.label(f, Kind::Theorem, idx)?;
+            writeln!(f)?;
+        }
+
+        writeln!(f, "largest proofs:")?;
+        for proof in stats.largest_proofs(self.n) {
+            write!(f, "  {:>12}  {:>8}  ", proof.bytes, proof.commands)?;
+            self.label(f, proof.item.0, proof.item.1)?;
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn section_sizes(mmb: &Mmb) -> SectionSizes {
+    let file = mmb.file().len();
+    let proofs_ptr = mmb.proofs_ptr();
+
+    // The proof section ends with the end command of the statement stream.
+    // Tables can be anywhere after it, and the index does not have to follow
+    // it directly.
+    let mut statements = mmb.statements();
+    statements.by_ref().for_each(drop);
+
+    let mut proofs_end = proofs_ptr + statements.offset();
+
+    if statements.error().is_none() {
+        proofs_end += 1;
+    }
+
+    let index = match mmb.index() {
+        Some(_) => file.saturating_sub(proofs_end),
+        None => 0,
+    };
+
+    let tables = HEADER_SIZE + mmb.sorts().len() + mmb.terms().len() + mmb.theorems().len();
+
+    SectionSizes {
+        header: HEADER_SIZE,
+        sorts: mmb.sorts().len(),
+        terms: mmb.terms().len(),
+        theorems: mmb.theorems().len(),
+        declarations: proofs_ptr.saturating_sub(tables),
+        proofs: proofs_end - proofs_ptr,
+        index,
+    }
+}
+
+fn most_used(uses: &[u64], n: usize) -> Vec<(u64, u64)> {
+    let mut most: Vec<_> = (0..)
+        .zip(uses.iter().copied())
+        .filter(|&(_, count)| count > 0)
+        .collect();
+
+    most.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    most.truncate(n);
+
+    most
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use super::*;
+    use crate::opcode::Unify;
+    use crate::testing;
+
+    /// Add a definition `nn` and a theorem `thm` with proofs to
+    /// `testing::logic`.
+    fn file() -> Vec<u8> {
+        let mut builder = testing::logic();
+        let not = 1;
+
+        builder.def(
+            "nn",
+            0,
+            &[0],
+            testing::unify(&[(Unify::Term, not), (Unify::Term, not), (Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Term, not), (Proof::Term, not)]),
+        );
+        builder.theorem(
+            "thm",
+            &[0],
+            testing::unify(&[(Unify::Ref, 0)]),
+            testing::proof(&[(Proof::Ref, 0), (Proof::Dummy, 0x10000), (Proof::Thm, 0)]),
+        );
+
+        builder.build()
+    }
+
+    #[test]
+    fn section_sizes() {
+        let file = file();
+        let stats = Stats::new(&Mmb::from(&file).unwrap());
+
+        assert_eq!(
+            stats.sections,
+            SectionSizes {
+                header: 40,
+                sorts: 1,
+                terms: 24,
+                theorems: 16,
+                declarations: 82,
+                proofs: 45,
+                index: 141,
+            }
+        );
+    }
+
+    #[test]
+    fn appended_tables_are_not_part_of_the_proof_section() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+
+        // Appending a table in front of a new index does not change the size
+        // of the proof section.
+        let appended = crate::index::append_table(&mmb, 0x74736554, |_| alloc::vec![0; 8]);
+        let stats = Stats::new(&Mmb::from(&appended).unwrap());
+
+        assert_eq!(stats.sections.proofs, 45);
+        assert_eq!(stats.sections.index, appended.len() - mmb.proofs_ptr() - 45);
+    }
+
+    #[test]
+    fn statement_counts() {
+        let file = file();
+        let stats = Stats::new(&Mmb::from(&file).unwrap());
+
+        assert_eq!(
+            stats.statements,
+            StatementCounts {
+                sorts: 1,
+                terms: 2,
+                defs: 1,
+                axioms: 1,
+                theorems: 1,
+                ..StatementCounts::default()
+            }
+        );
+        assert_eq!(stats.term_uses, [0, 2, 0]);
+        assert_eq!(stats.theorem_uses, [1, 0]);
+    }
+
+    #[test]
+    fn proof_sizes_and_operand_widths() {
+        let file = file();
+        let stats = Stats::new(&Mmb::from(&file).unwrap());
+
+        // The end commands are counted as commands without operand.
+        assert_eq!(stats.operand_widths, [5, 2, 0, 1]);
+        assert_eq!(
+            stats.proofs,
+            [
+                ProofSize {
+                    statement: Statement::Thm,
+                    offset: 31,
+                    item: (Kind::Theorem, 1),
+                    bytes: 8,
+                    commands: 4,
+                    heap: 2,
+                },
+                ProofSize {
+                    statement: Statement::TermDef,
+                    offset: 20,
+                    item: (Kind::Term, 2),
+                    bytes: 6,
+                    commands: 4,
+                    heap: 1,
+                },
+            ]
+        );
+        assert_eq!(stats.total_proof_commands(), 8);
+        assert_eq!(stats.max_proof_commands(), 4);
+        assert_eq!(stats.max_heap(), 2);
+    }
+
+    #[test]
+    fn report() {
+        let file = file();
+        let mmb = Mmb::from(&file).unwrap();
+        let names = mmb.index().unwrap().name_table().unwrap();
+        let stats = Stats::new(&mmb);
+
+        let report = stats.report(Some(&names), 1).to_string();
+
+        assert_eq!(
+            report.lines().collect::<Vec<_>>(),
+            [
+                "sections:",
+                "  header                  40",
+                "  sorts                    1",
+                "  terms                   24",
+                "  theorems                16",
+                "  declarations            82",
+                "  proofs                  45",
+                "  index                  141",
+                "statements:",
+                "  sort                     1",
+                "  term                     2",
+                "  def                      1",
+                "  local def                0",
+                "  axiom                    1",
+                "  theorem                  1",
+                "  local theorem            0",
+                "proofs:",
+                "  count                    2",
+                "  commands                 8",
+                "  average                4.0",
+                "  maximum                  4",
+                "  maximum heap             2",
+                "operand widths:",
+                "  0 bytes                  5",
+                "  1 bytes                  2",
+                "  2 bytes                  0",
+                "  4 bytes                  1",
+                "most used terms:",
+                "             2  not",
+                "most used theorems:",
+                "             1  ax",
+                "largest proofs:",
+                "             8         4  thm",
+            ]
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use core::convert::TryInto;
+
+use crate::command::{CommandIter, UnifyCommandIter};
+
+/// An entry of the term table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TermEntry<'a> {
+    /// The sort of the term, where the high bit is set for definitions.
+    pub sort: u8,
+    /// The slice containing the binders of the term, 8 bytes each.
+    pub binders: &'a [u8],
+    /// The return type of the term.
+    pub ret_ty: u64,
+    /// The slice starting at the unify stream of the definition, which is empty
+    /// if the term is not a definition.
+    pub unify: &'a [u8],
+}
+
+/// An entry of the theorem table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TheoremEntry<'a> {
+    /// The slice containing the binders of the theorem, 8 bytes each.
+    pub binders: &'a [u8],
+    /// The slice starting at the unify stream of the theorem.
+    pub unify: &'a [u8],
+}
+
+impl<'a> TermEntry<'a> {
+    /// Returns the number of binders of the term.
+    pub fn num_args(&self) -> usize {
+        self.binders.len() / 8
+    }
+
+    /// Returns the index of the sort of the term.
+    pub fn sort_idx(&self) -> u8 {
+        self.sort & 0x7F
+    }
+
+    /// Returns whether the term is a definition.
+    pub fn is_def(&self) -> bool {
+        self.sort & 0x80 != 0
+    }
+
+    /// Returns an iterator over the binders of the term.
+    pub fn binders(&self) -> impl Iterator<Item = u64> + 'a {
+        binders(self.binders)
+    }
+
+    /// Returns an iterator over the unify stream of the definition, with
+    /// offsets relative to the start of the unify stream.
+    pub fn unify_commands(&self) -> UnifyCommandIter<'a> {
+        CommandIter::new(self.unify, 0)
+    }
+}
+
+impl<'a> TheoremEntry<'a> {
+    /// Returns the number of binders of the theorem.
+    pub fn num_args(&self) -> usize {
+        self.binders.len() / 8
+    }
+
+    /// Returns an iterator over the binders of the theorem.
+    pub fn binders(&self) -> impl Iterator<Item = u64> + 'a {
+        binders(self.binders)
+    }
+
+    /// Returns an iterator over the unify stream of the theorem, with offsets
+    /// relative to the start of the unify stream.
+    pub fn unify_commands(&self) -> UnifyCommandIter<'a> {
+        CommandIter::new(self.unify, 0)
+    }
+}
+
+fn binders(slice: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    slice
+        .chunks_exact(8)
+        .filter_map(|chunk| chunk.try_into().ok())
+        .map(u64::from_le_bytes)
+}
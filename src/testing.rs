@@ -0,0 +1,301 @@
+//! A builder for small proof files, used by the tests.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use crate::index::NAME_TABLE_ID;
+use crate::opcode::{Proof, Statement, Unify};
+
+/// Encode a unify stream, terminated by an end command.
+pub(crate) fn unify(commands: &[(Unify, u32)]) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    for &(opcode, operand) in commands {
+        command(&mut data, opcode as u8, operand);
+    }
+
+    data.push(0);
+    data
+}
+
+/// Encode a proof stream, terminated by an end command.
+pub(crate) fn proof(commands: &[(Proof, u32)]) -> Vec<u8> {
+    let mut data = Vec::new();
+
+    for &(opcode, operand) in commands {
+        command(&mut data, opcode as u8, operand);
+    }
+
+    data.push(0);
+    data
+}
+
+/// A builder with the sort `wff`, the terms `im` with two arguments and `not`
+/// with one argument, and the axiom `ax` with one argument, to which tests add
+/// the items they need.
+pub(crate) fn logic() -> Builder {
+    let mut builder = Builder::new();
+    let wff = builder.sort("wff");
+    builder.term("im", wff, &[0, 0]);
+    builder.term("not", wff, &[0]);
+    builder.axiom("ax", &[0], unify(&[(Unify::Ref, 0)]), Vec::new());
+
+    builder
+}
+
+/// Returns the pointer to the index from the header of a proof file.
+pub(crate) fn index_ptr(file: &[u8]) -> usize {
+    u64::from_le_bytes(file[32..40].try_into().unwrap()) as usize
+}
+
+/// Returns the offset of the entry at the given position in the name table of
+/// a file built by `Builder`, counting the sorts first, then the terms and then
+/// the theorems.
+pub(crate) fn name_entry(file: &[u8], position: usize) -> usize {
+    index_ptr(file) + 8 + 16 + 16 * position
+}
+
+/// Encode a single command, using the smallest width for the operand.
+pub(crate) fn command(data: &mut Vec<u8>, opcode: u8, operand: u32) {
+    if operand == 0 {
+        data.push(opcode);
+    } else if operand <= 0xFF {
+        data.push(opcode | 0x40);
+        data.push(operand as u8);
+    } else if operand <= 0xFFFF {
+        data.push(opcode | 0x80);
+        data.extend_from_slice(&(operand as u16).to_le_bytes());
+    } else {
+        data.push(opcode | 0xC0);
+        data.extend_from_slice(&operand.to_le_bytes());
+    }
+}
+
+struct Term {
+    sort: u8,
+    binders: Vec<u64>,
+    unify: Option<Vec<u8>>,
+}
+
+struct Theorem {
+    binders: Vec<u64>,
+    unify: Vec<u8>,
+}
+
+/// A builder for a proof file, where items are declared in the order the
+/// methods are called.
+#[derive(Default)]
+pub(crate) struct Builder {
+    sorts: Vec<u8>,
+    terms: Vec<Term>,
+    theorems: Vec<Theorem>,
+    statements: Vec<(Statement, Vec<u8>)>,
+    names: [Vec<&'static str>; 3],
+}
+
+impl Builder {
+    pub(crate) fn new() -> Builder {
+        Builder::default()
+    }
+
+    /// Declare a sort and return its index.
+    pub(crate) fn sort(&mut self, name: &'static str) -> u8 {
+        self.sorts.push(0);
+        self.names[0].push(name);
+        self.statements.push((Statement::Sort, Vec::new()));
+
+        (self.sorts.len() - 1) as u8
+    }
+
+    /// Declare a term with the given sort and binders and return its index.
+    pub(crate) fn term(&mut self, name: &'static str, sort: u8, binders: &[u64]) -> u32 {
+        self.push_term(name, sort, binders, None, Vec::new())
+    }
+
+    /// Declare a definition with the given sort, binders, unify stream and
+    /// proof stream and return its index.
+    pub(crate) fn def(
+        &mut self,
+        name: &'static str,
+        sort: u8,
+        binders: &[u64],
+        unify: Vec<u8>,
+        proof: Vec<u8>,
+    ) -> u32 {
+        self.push_term(name, sort | 0x80, binders, Some(unify), proof)
+    }
+
+    /// Declare an axiom and return its index.
+    pub(crate) fn axiom(
+        &mut self,
+        name: &'static str,
+        binders: &[u64],
+        unify: Vec<u8>,
+        proof: Vec<u8>,
+    ) -> u32 {
+        self.push_theorem(Statement::Axiom, name, binders, unify, proof)
+    }
+
+    /// Declare a theorem and return its index.
+    pub(crate) fn theorem(
+        &mut self,
+        name: &'static str,
+        binders: &[u64],
+        unify: Vec<u8>,
+        proof: Vec<u8>,
+    ) -> u32 {
+        self.push_theorem(Statement::Thm, name, binders, unify, proof)
+    }
+
+    fn push_term(
+        &mut self,
+        name: &'static str,
+        sort: u8,
+        binders: &[u64],
+        unify: Option<Vec<u8>>,
+        proof: Vec<u8>,
+    ) -> u32 {
+        self.terms.push(Term {
+            sort,
+            binders: binders.to_vec(),
+            unify,
+        });
+        self.names[1].push(name);
+        self.statements.push((Statement::TermDef, proof));
+
+        (self.terms.len() - 1) as u32
+    }
+
+    fn push_theorem(
+        &mut self,
+        kind: Statement,
+        name: &'static str,
+        binders: &[u64],
+        unify: Vec<u8>,
+        proof: Vec<u8>,
+    ) -> u32 {
+        self.theorems.push(Theorem {
+            binders: binders.to_vec(),
+            unify,
+        });
+        self.names[2].push(name);
+        self.statements.push((kind, proof));
+
+        (self.theorems.len() - 1) as u32
+    }
+
+    /// Build the proof file, with an index containing a name table.
+    pub(crate) fn build(&self) -> Vec<u8> {
+        let terms_ptr = 40 + self.sorts.len();
+        let theorems_ptr = terms_ptr + 8 * self.terms.len();
+        let data_ptr = theorems_ptr + 8 * self.theorems.len();
+
+        let mut tables = Vec::new();
+        let mut data = Vec::new();
+
+        for term in &self.terms {
+            let ptr = (data_ptr + data.len()) as u32;
+            tables.extend_from_slice(&(term.binders.len() as u16).to_le_bytes());
+            tables.push(term.sort);
+            tables.push(0);
+            tables.extend_from_slice(&ptr.to_le_bytes());
+
+            for binder in &term.binders {
+                data.extend_from_slice(&binder.to_le_bytes());
+            }
+
+            data.extend_from_slice(&((term.sort as u64 & 0x7F) << 56).to_le_bytes());
+
+            if let Some(unify) = &term.unify {
+                data.extend_from_slice(unify);
+            }
+        }
+
+        for theorem in &self.theorems {
+            let ptr = (data_ptr + data.len()) as u32;
+            tables.extend_from_slice(&(theorem.binders.len() as u16).to_le_bytes());
+            tables.extend_from_slice(&0u16.to_le_bytes());
+            tables.extend_from_slice(&ptr.to_le_bytes());
+
+            for binder in &theorem.binders {
+                data.extend_from_slice(&binder.to_le_bytes());
+            }
+
+            data.extend_from_slice(&theorem.unify);
+        }
+
+        let proofs_ptr = data_ptr + data.len();
+
+        let mut proofs = Vec::new();
+        let mut offsets = Vec::new();
+
+        for (kind, proof) in &self.statements {
+            offsets.push((proofs_ptr + proofs.len()) as u64);
+
+            let len = proof.len() + 5;
+            proofs.push(*kind as u8 | 0xC0);
+            proofs.extend_from_slice(&(len as u32).to_le_bytes());
+            proofs.extend_from_slice(proof);
+        }
+
+        proofs.push(0);
+
+        let index_ptr = proofs_ptr + proofs.len();
+        let names_ptr = index_ptr + 8 + 16;
+        let num_names = self.statements.len();
+        let mut strings_ptr = names_ptr + 16 * num_names;
+
+        let mut index = Vec::new();
+        index.extend_from_slice(&1u64.to_le_bytes());
+        index.extend_from_slice(&NAME_TABLE_ID.to_le_bytes());
+        index.extend_from_slice(&0u32.to_le_bytes());
+        index.extend_from_slice(&(names_ptr as u64).to_le_bytes());
+
+        // Name table entries are grouped by kind, while the offsets of the
+        // statements are in declaration order.
+        let mut strings = Vec::new();
+        let mut declared = [0; 3];
+
+        for names in &self.names {
+            for name in names {
+                index.extend_from_slice(&0u64.to_le_bytes());
+                index.extend_from_slice(&(strings_ptr as u64).to_le_bytes());
+                strings_ptr += name.len() + 1;
+                strings.extend_from_slice(name.as_bytes());
+                strings.push(0);
+            }
+        }
+
+        for ((kind, _), &offset) in self.statements.iter().zip(&offsets) {
+            let kind = match kind {
+                Statement::Sort => 0,
+                Statement::TermDef => 1,
+                _ => 2,
+            };
+
+            let position: usize = self.names[..kind].iter().map(|names| names.len()).sum();
+            let entry = 8 + 16 + 16 * (position + declared[kind]);
+            index[entry..entry + 8].copy_from_slice(&offset.to_le_bytes());
+            declared[kind] += 1;
+        }
+
+        index.extend_from_slice(&strings);
+
+        let mut file = vec![0x4d, 0x4d, 0x30, 0x42, 1, self.sorts.len() as u8, 0, 0];
+        file.extend_from_slice(&(self.terms.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(self.theorems.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(terms_ptr as u32).to_le_bytes());
+        file.extend_from_slice(&(theorems_ptr as u32).to_le_bytes());
+        file.extend_from_slice(&(proofs_ptr as u32).to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&(index_ptr as u64).to_le_bytes());
+        file.extend_from_slice(&self.sorts);
+        file.extend_from_slice(&tables);
+        file.extend_from_slice(&data);
+        file.extend_from_slice(&proofs);
+        file.extend_from_slice(&index);
+
+        file
+    }
+}
@@ -0,0 +1,170 @@
+use crate::index::EntryIterator;
+
+/// The versions of the proof file format this crate can parse.
+pub const SUPPORTED_VERSIONS: &[u8] = &[1];
+
+/// The latest version of the proof file format this crate can parse.
+pub const LATEST_VERSION: u8 = 1;
+
+/// How to treat proof files of a version this crate does not know.
+///
+/// Versions older than the first supported version are always rejected.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Compatibility {
+    /// Only accept the versions in `SUPPORTED_VERSIONS`. This is the default.
+    #[default]
+    Strict,
+    /// Also accept versions newer than `LATEST_VERSION`, reporting them with
+    /// `Warning::NewerVersion`.
+    ///
+    /// The file is parsed as if it had the latest version. Statements of kinds
+    /// added in newer versions are not misparsed, but stop the parsing of the
+    /// proof section at that statement, as if it was malformed.
+    AcceptNewer,
+}
+
+impl Compatibility {
+    /// Check whether a proof file of the given version is accepted.
+    pub fn check(self, version: u8) -> Result<(), FormatError> {
+        let newer = self == Compatibility::AcceptNewer && version > LATEST_VERSION;
+
+        if SUPPORTED_VERSIONS.contains(&version) || newer {
+            Ok(())
+        } else {
+            Err(FormatError::UnsupportedVersion(version))
+        }
+    }
+}
+
+/// An error that prevented a proof file from being opened.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FormatError {
+    /// The file has a version that is not accepted by the chosen
+    /// `Compatibility`.
+    UnsupportedVersion(u8),
+    /// The file header or the tables it points to are malformed.
+    Malformed,
+}
+
+/// A part of a proof file this crate does not understand, which is skipped
+/// when the file is processed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Warning {
+    /// The file has a version newer than `LATEST_VERSION`.
+    NewerVersion(u8),
+    /// The index contains a table with an id this crate does not know.
+    UnknownIndexTable(u32),
+}
+
+/// An iterator over the warnings for a proof file.
+pub struct Warnings<'a> {
+    pub(crate) version: Option<u8>,
+    pub(crate) entries: Option<EntryIterator<'a>>,
+}
+
+impl<'a> Iterator for Warnings<'a> {
+    type Item = Warning;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(version) = self.version.take() {
+            return Some(Warning::NewerVersion(version));
+        }
+
+        let entry = self.entries.as_mut()?.find(|entry| !entry.is_known())?;
+
+        Some(Warning::UnknownIndexTable(entry.id()))
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::{index, testing, Mmb};
+
+    fn file(version: u8) -> Vec<u8> {
+        let mut file = testing::logic().build();
+        file[4] = version;
+        file
+    }
+
+    #[test]
+    fn check() {
+        let newer = LATEST_VERSION + 1;
+
+        assert_eq!(Compatibility::default(), Compatibility::Strict);
+
+        for &version in SUPPORTED_VERSIONS {
+            assert_eq!(Compatibility::Strict.check(version), Ok(()));
+            assert_eq!(Compatibility::AcceptNewer.check(version), Ok(()));
+        }
+
+        assert_eq!(
+            Compatibility::Strict.check(newer),
+            Err(FormatError::UnsupportedVersion(newer))
+        );
+        assert_eq!(Compatibility::AcceptNewer.check(newer), Ok(()));
+        assert_eq!(Compatibility::AcceptNewer.check(u8::MAX), Ok(()));
+
+        // Older versions are rejected by every policy.
+        for &compatibility in &[Compatibility::Strict, Compatibility::AcceptNewer] {
+            assert_eq!(
+                compatibility.check(0),
+                Err(FormatError::UnsupportedVersion(0))
+            );
+        }
+    }
+
+    #[test]
+    fn opening_files() {
+        assert!(Mmb::from(&file(1)).is_some());
+        assert!(Mmb::from(&file(0)).is_none());
+        assert!(Mmb::from(&file(2)).is_none());
+
+        assert_eq!(
+            Mmb::with_compatibility(&file(2), Compatibility::Strict).err(),
+            Some(FormatError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            Mmb::with_compatibility(&file(2)[..20], Compatibility::AcceptNewer).err(),
+            Some(FormatError::Malformed)
+        );
+    }
+
+    #[test]
+    fn no_warnings_for_supported_files() {
+        let file = file(1);
+        let mmb = Mmb::from(&file).unwrap();
+
+        assert_eq!(mmb.warnings().count(), 0);
+    }
+
+    #[test]
+    fn newer_version_warning() {
+        let file = file(2);
+        let mmb = Mmb::with_compatibility(&file, Compatibility::AcceptNewer).unwrap();
+
+        assert_eq!(
+            mmb.warnings().collect::<Vec<_>>(),
+            [Warning::NewerVersion(2)]
+        );
+    }
+
+    #[test]
+    fn unknown_index_table() {
+        let id = u32::from_le_bytes(*b"Xtra");
+
+        let file = file(2);
+        let mmb = Mmb::with_compatibility(&file, Compatibility::AcceptNewer).unwrap();
+        let file = index::append_table(&mmb, id, |_| alloc::vec![0; 8]);
+        let mmb = Mmb::with_compatibility(&file, Compatibility::AcceptNewer).unwrap();
+
+        // The newer version is reported first, followed by the unknown tables
+        // in the order of the index, skipping the known name table.
+        assert_eq!(
+            mmb.warnings().collect::<Vec<_>>(),
+            [Warning::NewerVersion(2), Warning::UnknownIndexTable(id)]
+        );
+    }
+}
@@ -1,17 +1,105 @@
+use core::ops::ControlFlow;
+
+use crate::error::ParseError;
 use crate::opcode::{Command, Proof, Statement, Unify};
 
 pub trait UnifyStream {
+    /// Called before the first command of a new unify stream is pushed.
+    fn start(&mut self) {}
+
     fn push(&mut self, command: Command<Unify>);
 
     fn done(&self) -> (usize, usize);
 }
 
 pub trait ProofStream {
+    /// Called before the first command of a new proof stream is pushed.
+    fn start(&mut self) {}
+
     fn push(&mut self, command: Command<Proof>);
 
     fn done(&self) -> (usize, usize);
 }
 
+/// A unify and proof stream that discards all commands.
+///
+/// This is used for visitors that do not provide a stream of their own.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Discard;
+
+impl UnifyStream for Discard {
+    fn push(&mut self, _command: Command<Unify>) {}
+
+    fn done(&self) -> (usize, usize) {
+        (0, 0)
+    }
+}
+
+impl ProofStream for Discard {
+    fn push(&mut self, _command: Command<Proof>) {}
+
+    fn done(&self) -> (usize, usize) {
+        (0, 0)
+    }
+}
+
+/// The parts of a proof file that are visited by `Mmb::visit_with`.
+///
+/// All parts are visited by default. Sections that are skipped are not parsed
+/// at all, so errors in them are not reported either.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VisitOptions {
+    /// Visit the sort table.
+    pub sorts: bool,
+    /// Visit the statements of the proof section.
+    pub statements: bool,
+    /// Visit the term table.
+    pub terms: bool,
+    /// Visit the theorem table.
+    pub theorems: bool,
+    /// Decode the proof streams of the statements. If disabled, the proof
+    /// indices passed to `Visitor::parse_statement` are always `None`.
+    pub proofs: bool,
+}
+
+impl Default for VisitOptions {
+    fn default() -> VisitOptions {
+        VisitOptions {
+            sorts: true,
+            statements: true,
+            terms: true,
+            theorems: true,
+            proofs: true,
+        }
+    }
+}
+
+/// The result of a visitor callback.
+///
+/// Returning `ControlFlow::Break` stops the traversal without an error, while
+/// returning an error stops it and hands the error to the caller.
+pub type VisitResult<E> = Result<ControlFlow<()>, E>;
+
+/// An error that stopped the traversal of a proof file.
+#[derive(Debug)]
+pub enum VisitError<'a, E> {
+    /// The proof file could not be parsed.
+    Parse(nom::Err<ParseError<'a>>),
+    /// A callback of the visitor returned an error.
+    Visitor(E),
+}
+
+impl<'a, E> From<nom::Err<ParseError<'a>>> for VisitError<'a, E> {
+    fn from(e: nom::Err<ParseError<'a>>) -> VisitError<'a, E> {
+        VisitError::Parse(e)
+    }
+}
+
+/// A visitor over the sorts, statements, terms and theorems of a proof file.
+///
+/// All methods have default implementations that ignore their input, so a
+/// visitor only needs to implement the callbacks it is interested in. Visitors
+/// that do not collect streams can use `Discard` for the stream types.
 pub trait Visitor<'a> {
     type Binder: From<u64>;
     type Sort: From<u8>;
@@ -19,35 +107,312 @@ pub trait Visitor<'a> {
     type Unify: UnifyStream;
     type Proof: ProofStream;
 
-    fn parse_sort(&mut self, sort: Self::Sort);
+    /// The error returned by the callbacks of the visitor.
+    type Error;
+
+    fn parse_sort(&mut self, _sort: Self::Sort) -> VisitResult<Self::Error> {
+        Ok(ControlFlow::Continue(()))
+    }
 
     fn parse_statement(
         &mut self,
-        statement: Self::Statement,
-        offset: usize,
-        slice: &'a [u8],
-        proof: Option<(usize, usize)>,
-    );
+        _statement: Self::Statement,
+        _offset: usize,
+        _slice: &'a [u8],
+        _proof: Option<(usize, usize)>,
+    ) -> VisitResult<Self::Error> {
+        Ok(ControlFlow::Continue(()))
+    }
 
-    fn try_reserve_binder_slice(&mut self, nr: usize) -> Option<(&mut [Self::Binder], usize)>;
+    /// Reserve space for `nr` binders, and return the slice to fill together
+    /// with the index of its first element, or `None` if out of memory.
+    ///
+    /// A slice shorter than `nr` receives only the first binders, and the
+    /// binder range passed to `parse_term` or `parse_theorem` covers only the
+    /// binders that were stored. By default, binders are discarded by returning
+    /// an empty slice, so the range is always empty.
+    fn try_reserve_binder_slice(&mut self, _nr: usize) -> Option<(&mut [Self::Binder], usize)> {
+        Some((&mut [], 0))
+    }
 
-    fn start_unify_stream(&mut self) -> &mut Self::Unify;
+    /// Return the stream that receives the next unify stream, or `None` to
+    /// discard it, which is the default.
+    fn start_unify_stream(&mut self) -> Option<&mut Self::Unify> {
+        None
+    }
 
-    fn start_proof_stream(&mut self) -> &mut Self::Proof;
+    /// Return the stream that receives the next proof stream, or `None` to
+    /// discard it, which is the default.
+    fn start_proof_stream(&mut self) -> Option<&mut Self::Proof> {
+        None
+    }
 
     fn parse_term(
         &mut self,
-        sort_idx: u8,
-        binders: (usize, usize),
-        ret_ty: Self::Binder,
-        unify: &'a [u8],
-        unify_indices: (usize, usize),
-    );
+        _sort_idx: u8,
+        _binders: (usize, usize),
+        _ret_ty: Self::Binder,
+        _unify: &'a [u8],
+        _unify_indices: (usize, usize),
+    ) -> VisitResult<Self::Error> {
+        Ok(ControlFlow::Continue(()))
+    }
 
     fn parse_theorem(
         &mut self,
-        binders: (usize, usize),
-        unify: &'a [u8],
-        unify_indices: (usize, usize),
-    );
+        _binders: (usize, usize),
+        _unify: &'a [u8],
+        _unify_indices: (usize, usize),
+    ) -> VisitResult<Self::Error> {
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::testing::{self, Builder};
+    use crate::Mmb;
+
+    /// Records the binder ranges of terms and theorems, and reserves binders
+    /// only if `binders` is set.
+    #[derive(Default)]
+    struct Ranges {
+        binders: Option<Vec<u64>>,
+        ranges: Vec<(usize, usize)>,
+    }
+
+    impl<'a> Visitor<'a> for Ranges {
+        type Binder = u64;
+        type Sort = u8;
+        type Statement = Statement;
+        type Unify = Discard;
+        type Proof = Discard;
+        type Error = ();
+
+        fn try_reserve_binder_slice(&mut self, nr: usize) -> Option<(&mut [u64], usize)> {
+            match &mut self.binders {
+                Some(binders) => {
+                    let offset = binders.len();
+                    binders.resize(offset + nr, 0);
+
+                    Some((&mut binders[offset..], offset))
+                }
+                None => Some((&mut [], 0)),
+            }
+        }
+
+        fn parse_term(
+            &mut self,
+            _sort_idx: u8,
+            binders: (usize, usize),
+            _ret_ty: u64,
+            _unify: &'a [u8],
+            _unify_indices: (usize, usize),
+        ) -> VisitResult<()> {
+            self.ranges.push(binders);
+            Ok(ControlFlow::Continue(()))
+        }
+
+        fn parse_theorem(
+            &mut self,
+            binders: (usize, usize),
+            _unify: &'a [u8],
+            _unify_indices: (usize, usize),
+        ) -> VisitResult<()> {
+            self.ranges.push(binders);
+            Ok(ControlFlow::Continue(()))
+        }
+    }
+
+    fn file() -> Vec<u8> {
+        let mut builder = Builder::new();
+        let wff = builder.sort("wff");
+        builder.term("im", wff, &[1, 1]);
+        builder.axiom(
+            "ax",
+            &[1],
+            testing::unify(&[(crate::opcode::Unify::Ref, 0)]),
+            Vec::new(),
+        );
+
+        builder.build()
+    }
+
+    #[test]
+    fn discarded_binders_have_empty_ranges() {
+        let file = file();
+        let mut visitor = Ranges::default();
+        let flow = Mmb::from(&file).unwrap().visit(&mut visitor).unwrap();
+
+        assert_eq!(flow, ControlFlow::Continue(()));
+
+        assert_eq!(visitor.ranges, [(0, 0), (0, 0)]);
+    }
+
+    #[test]
+    fn reserved_binders_have_ranges() {
+        let file = file();
+        let mut visitor = Ranges {
+            binders: Some(Vec::new()),
+            ..Ranges::default()
+        };
+        let flow = Mmb::from(&file).unwrap().visit(&mut visitor).unwrap();
+
+        assert_eq!(flow, ControlFlow::Continue(()));
+
+        assert_eq!(visitor.ranges, [(0, 2), (2, 3)]);
+        assert_eq!(visitor.binders, Some(alloc::vec![1, 1, 1]));
+    }
+
+    #[test]
+    fn malformed_statements_are_errors() {
+        let mut file = file();
+        let proofs_ptr = Mmb::from(&file).unwrap().proofs_ptr();
+
+        // The length of the last statement exceeds the file.
+        let ax = proofs_ptr + 10;
+        file[ax + 1..ax + 5].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mmb = Mmb::from(&file).unwrap();
+
+        assert!(matches!(
+            mmb.visit(&mut Ranges::default()),
+            Err(VisitError::Parse(_))
+        ));
+        assert!(matches!(
+            mmb.visit_statements(&mut Ranges::default()),
+            Err(VisitError::Parse(_))
+        ));
+        assert!(matches!(
+            mmb.visit_in_declaration_order(&mut Ranges::default()),
+            Err(VisitError::Parse(_))
+        ));
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Event {
+        Sort,
+        /// A term with the given number of binders.
+        Term(usize),
+        /// A theorem with the given number of binders.
+        Theorem(usize),
+        Statement(Statement, usize),
+    }
+
+    /// Records the callbacks in the order they are called.
+    #[derive(Default)]
+    struct Recorder {
+        binders: Vec<u64>,
+        events: Vec<Event>,
+    }
+
+    impl<'a> Visitor<'a> for Recorder {
+        type Binder = u64;
+        type Sort = u8;
+        type Statement = Statement;
+        type Unify = Discard;
+        type Proof = Discard;
+        type Error = ();
+
+        fn parse_sort(&mut self, _sort: u8) -> VisitResult<()> {
+            self.events.push(Event::Sort);
+            Ok(ControlFlow::Continue(()))
+        }
+
+        fn parse_statement(
+            &mut self,
+            statement: Statement,
+            offset: usize,
+            _slice: &'a [u8],
+            _proof: Option<(usize, usize)>,
+        ) -> VisitResult<()> {
+            self.events.push(Event::Statement(statement, offset));
+            Ok(ControlFlow::Continue(()))
+        }
+
+        fn try_reserve_binder_slice(&mut self, nr: usize) -> Option<(&mut [u64], usize)> {
+            let offset = self.binders.len();
+            self.binders.resize(offset + nr, 0);
+
+            Some((&mut self.binders[offset..], offset))
+        }
+
+        fn parse_term(
+            &mut self,
+            _sort_idx: u8,
+            binders: (usize, usize),
+            _ret_ty: u64,
+            _unify: &'a [u8],
+            _unify_indices: (usize, usize),
+        ) -> VisitResult<()> {
+            self.events.push(Event::Term(binders.1 - binders.0));
+            Ok(ControlFlow::Continue(()))
+        }
+
+        fn parse_theorem(
+            &mut self,
+            binders: (usize, usize),
+            _unify: &'a [u8],
+            _unify_indices: (usize, usize),
+        ) -> VisitResult<()> {
+            self.events.push(Event::Theorem(binders.1 - binders.0));
+            Ok(ControlFlow::Continue(()))
+        }
+    }
+
+    #[test]
+    fn items_are_visited_before_their_statements() {
+        let file = testing::logic().build();
+        let mut recorder = Recorder::default();
+        let flow = Mmb::from(&file)
+            .unwrap()
+            .visit_in_declaration_order(&mut recorder)
+            .unwrap();
+
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert_eq!(
+            recorder.events,
+            [
+                Event::Sort,
+                Event::Statement(Statement::Sort, 0),
+                Event::Term(2),
+                Event::Statement(Statement::TermDef, 5),
+                Event::Term(1),
+                Event::Statement(Statement::TermDef, 10),
+                Event::Theorem(1),
+                Event::Statement(Statement::Axiom, 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn items_without_statement_are_visited_last() {
+        let mut file = testing::logic().build();
+        let proofs_ptr = Mmb::from(&file).unwrap().proofs_ptr();
+
+        // End the statement stream before the term `not`.
+        file[proofs_ptr + 10] = 0;
+
+        let mut recorder = Recorder::default();
+        let flow = Mmb::from(&file)
+            .unwrap()
+            .visit_in_declaration_order(&mut recorder)
+            .unwrap();
+
+        assert_eq!(flow, ControlFlow::Continue(()));
+        assert_eq!(
+            recorder.events,
+            [
+                Event::Sort,
+                Event::Statement(Statement::Sort, 0),
+                Event::Term(2),
+                Event::Statement(Statement::TermDef, 5),
+                Event::Term(1),
+                Event::Theorem(1),
+            ]
+        );
+    }
 }